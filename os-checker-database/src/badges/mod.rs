@@ -0,0 +1,169 @@
+use crate::{base_dir, remove_dir, utils::IndexMap, Batch, Result};
+use os_checker_types::Kind;
+use std::{collections::BTreeMap, fmt::Write, fs};
+
+#[cfg(test)]
+mod tests;
+
+const LABEL: &str = "os-checker";
+const BADGES_DIR: &str = "badges";
+
+/// 某个仓库或 package 在所有 targets 上的诊断数量
+#[derive(Debug, Default)]
+struct Counts {
+    total: usize,
+    kinds: IndexMap<Kind, usize>,
+}
+
+impl Counts {
+    fn add(&mut self, kind: Kind) {
+        self.total += 1;
+        *self.kinds.entry(kind).or_default() += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct RepoCounts {
+    repo: Counts,
+    /// 所有被检查的 packages，包括无诊断的 package
+    pkgs: BTreeMap<String, Counts>,
+}
+
+/// 生成 SVG 徽章，以便被检查的仓库在 README 中嵌入检查状态：
+/// * `badges/{user}/{repo}.svg`：仓库的诊断总数
+/// * `badges/{user}/{repo}/kind/{kind}.svg`：仓库每种诊断的数量
+/// * `badges/{user}/{repo}/pkg/{pkg}.svg`：package 的诊断总数
+/// * `badges/{user}/{repo}/pkg/{pkg}/{kind}.svg`：package 每种诊断的数量
+///
+/// kind 的文件名见 [`kind_file_name`]；检查过但无诊断的 kind 不生成徽章。
+/// 写入前清除 `badges/{user}/{repo}` 目录，以免降为 0 的 kind 或者移除的 package 留下过时的徽章。
+pub fn write_badges(batches: &[Batch]) -> Result<()> {
+    let mut repos = BTreeMap::<(String, String), RepoCounts>::new();
+    for Batch { json, .. } in batches {
+        let repo_pkg = |pkg_idx: usize| {
+            let pkg = &json.env.packages[pkg_idx];
+            let key = (pkg.repo.user.to_string(), pkg.repo.repo.to_string());
+            (key, pkg.name.to_string())
+        };
+        for cmd in &json.cmd {
+            let (key, pkg) = repo_pkg(cmd.package_idx);
+            repos.entry(key).or_default().pkgs.entry(pkg).or_default();
+        }
+        for d in &json.data {
+            let (key, pkg) = repo_pkg(json.cmd[d.cmd_idx].package_idx);
+            let counts = repos.entry(key).or_default();
+            counts.repo.add(d.kind);
+            counts.pkgs.entry(pkg).or_default().add(d.kind);
+        }
+    }
+
+    let dir = base_dir().join(BADGES_DIR);
+    for ((user, repo), counts) in &repos {
+        let user_dir = dir.join(user);
+        fs::create_dir_all(&user_dir)?;
+        fs::write(
+            user_dir.join(format!("{repo}.svg")),
+            total_badge(counts.repo.total),
+        )?;
+
+        let repo_dir = user_dir.join(repo);
+        remove_dir(&repo_dir)?;
+        write_kinds(&repo_dir.join("kind"), &counts.repo)?;
+        for (pkg, counts) in &counts.pkgs {
+            let pkg_dir = repo_dir.join("pkg");
+            fs::create_dir_all(&pkg_dir)?;
+            fs::write(
+                pkg_dir.join(format!("{pkg}.svg")),
+                total_badge(counts.total),
+            )?;
+            write_kinds(&pkg_dir.join(pkg), counts)?;
+        }
+    }
+    info!(repos = repos.len(), "badges 写入成功");
+    Ok(())
+}
+
+fn write_kinds(dir: &camino::Utf8Path, counts: &Counts) -> Result<()> {
+    if counts.kinds.is_empty() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir)?;
+    for (kind, count) in &counts.kinds {
+        let path = dir.join(format!("{}.svg", kind_file_name(*kind)));
+        fs::write(
+            path,
+            badge(kind.as_str(), &count.to_string(), color(*count)),
+        )?;
+    }
+    Ok(())
+}
+
+/// `Clippy(Warn)` => `clippy-warn`
+pub fn kind_file_name(kind: Kind) -> String {
+    let name: String = kind
+        .as_str()
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() => c.to_ascii_lowercase(),
+            _ => '-',
+        })
+        .collect();
+    name.split('-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn total_badge(total: usize) -> String {
+    match total {
+        0 => badge(LABEL, "pass", color(0)),
+        1 => badge(LABEL, "1 diagnostic", color(1)),
+        n => badge(LABEL, &format!("{n} diagnostics"), color(n)),
+    }
+}
+
+fn color(count: usize) -> &'static str {
+    match count {
+        0 => "#4c1",
+        1..=9 => "#dfb317",
+        10..=99 => "#fe7d37",
+        _ => "#e05d44",
+    }
+}
+
+/// 近似的文本宽度：11px Verdana 的平均字符宽度
+fn text_width(s: &str) -> usize {
+    s.chars().count() * 7 + 10
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// shields.io 风格的扁平徽章
+fn badge(label: &str, message: &str, color: &str) -> String {
+    let (lw, mw) = (text_width(label), text_width(message));
+    let width = lw + mw;
+    let (label, message) = (escape(label), escape(message));
+    let (lx, mx) = (lw * 5, lw * 10 + mw * 5);
+
+    let mut svg = String::with_capacity(1024);
+    _ = write!(
+        svg,
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+<title>{label}: {message}</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{lw}" height="20" fill="#555"/><rect x="{lw}" width="{mw}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="110">
+<text x="{lx}" y="150" fill="#010101" fill-opacity=".3" transform="scale(.1)">{label}</text><text x="{lx}" y="140" transform="scale(.1)">{label}</text>
+<text x="{mx}" y="150" fill="#010101" fill-opacity=".3" transform="scale(.1)">{message}</text><text x="{mx}" y="140" transform="scale(.1)">{message}</text>
+</g>
+</svg>
+"##
+    );
+    svg
+}
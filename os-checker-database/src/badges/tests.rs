@@ -0,0 +1,23 @@
+use super::*;
+
+#[test]
+fn kind_names() {
+    let names: Vec<_> = [Kind::ClippyWarn, Kind::SemverViolation, Kind::Udeps]
+        .into_iter()
+        .map(kind_file_name)
+        .collect();
+    assert_eq!(names, ["clippy-warn", "semver-violation", "udeps"]);
+}
+
+#[test]
+fn svg_badge() {
+    let svg = total_badge(0);
+    assert!(svg.contains(r#"aria-label="os-checker: pass""#));
+    assert!(svg.contains("#4c1"));
+
+    let svg = badge("a<b", "12", color(12));
+    assert!(svg.contains("a&lt;b: 12"));
+    assert!(svg.contains("#fe7d37"));
+    // label 3 chars + message 2 chars
+    assert!(svg.contains(r#"width="55""#));
+}
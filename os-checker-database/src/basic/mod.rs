@@ -79,7 +79,7 @@ pub fn all(json: &JsonOutput) -> Basic {
 }
 
 /// 按仓库的架构统计
-pub fn by_repo(json: &JsonOutput) -> Vec<(UserRepo, Basic)> {
+pub fn by_repo(json: &JsonOutput) -> Vec<(UserRepo<'_>, Basic)> {
     let map = group_by(&json.cmd, |cmd| repo_pkgidx(json, cmd.package_idx));
     let mut v = Vec::<(UserRepo, Basic)>::with_capacity(map.len());
 
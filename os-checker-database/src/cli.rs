@@ -0,0 +1,146 @@
+use crate::{manifest::Incremental, Result};
+use argh::FromArgs;
+use camino::Utf8PathBuf;
+use eyre::ensure;
+
+/// Generate the static JSON data for os-checker WebUI from os-checker outputs and the redb cache.
+/// All data are generated if no subcommand is given.
+#[derive(FromArgs, Debug)]
+pub struct Args {
+    /// a dir containing JSON outputs of os-checker, one for each batch. Default to batch.
+    #[argh(option, default = "Utf8PathBuf::from(\"batch\")")]
+    input: Utf8PathBuf,
+    /// the output dir. Default to ui.
+    #[argh(option, default = "Utf8PathBuf::from(\"ui\")")]
+    output: Utf8PathBuf,
+    /// the redb cache file. Default to cache.redb.
+    #[argh(option, default = "Utf8PathBuf::from(\"cache.redb\")")]
+    db: Utf8PathBuf,
+    /// remove the intermediate batch dir in the output dir and the redb file when finished
+    #[argh(switch)]
+    clear: bool,
+    /// only regenerate repos changed since the last generation, which is tracked by
+    /// manifest.json in the output dir. This doesn't work with subcommands.
+    #[argh(switch)]
+    incremental: bool,
+    #[argh(subcommand)]
+    sub_args: Option<SubArgs>,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand)]
+enum SubArgs {
+    Filetree(ArgsFiletree),
+    Home(ArgsHome),
+    Basic(ArgsBasic),
+    Stats(ArgsStats),
+    Targets(ArgsTargets),
+    Trend(ArgsTrend),
+    Report(ArgsReport),
+    Badges(ArgsBadges),
+    Perf(ArgsPerf),
+}
+
+/// Generate file-tree data and the ones in repos/user/repo.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "filetree")]
+struct ArgsFiletree {}
+
+/// Generate home data.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "home")]
+struct ArgsHome {}
+
+/// Generate basic.json and repos/user/repo/basic.json.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "basic")]
+struct ArgsBasic {}
+
+/// Generate pass_count_repo data.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "stats")]
+struct ArgsStats {}
+
+/// Generate targets data and user_repo.json from the redb cache.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "targets")]
+struct ArgsTargets {}
+
+/// Generate trend data from the check history in the redb cache.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "trend")]
+struct ArgsTrend {}
+
+/// Generate SVG badges of diagnostic counts for repos and packages.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "badges")]
+struct ArgsBadges {}
+
+/// Rank the slowest checkers, repos, packages and targets, with cache-hit ratios, in the last
+/// check item of the redb cache.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "perf")]
+struct ArgsPerf {
+    /// keep the top N slowest repos, packages, targets and commands. Default to 20.
+    #[argh(option, default = "crate::perf::TOP")]
+    top: usize,
+}
+
+/// Render all batches into a single offline HTML report.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "report")]
+struct ArgsReport {
+    /// the HTML file path. Default to report.html in the output dir.
+    #[argh(option)]
+    file: Option<Utf8PathBuf>,
+}
+
+impl Args {
+    pub fn execute(self) -> Result<()> {
+        debug!(?self);
+        crate::BASE_DIR.get_or_init(|| self.output.clone());
+        crate::CACHE_REDB.get_or_init(|| self.db.clone());
+
+        let batches = || crate::read_batches(&self.input);
+        match &self.sub_args {
+            None => {
+                let incr = Incremental::new(self.incremental)?;
+                // 先读取输入，再清除旧数据，以免输入有误时丢失旧数据
+                let batches = batches()?;
+                incr.clear()?;
+                crate::generate_all(&batches, &incr)?;
+                // 记录本次生成的仓库状态
+                incr.write()?;
+            }
+            Some(sub_args) => {
+                ensure!(
+                    !self.incremental,
+                    "--incremental only works when all data are generated"
+                );
+                let incr = &Incremental::disabled();
+                match sub_args {
+                    SubArgs::Filetree(_) => crate::gen_filetree(&batches()?, incr)?,
+                    SubArgs::Home(_) => crate::gen_home(&batches()?)?,
+                    SubArgs::Basic(_) => crate::gen_basic(&batches()?, incr)?,
+                    SubArgs::Stats(_) => crate::gen_stats(&batches()?)?,
+                    SubArgs::Targets(_) => crate::targets::do_resolves(incr)?,
+                    SubArgs::Trend(_) => crate::trend::write_trend()?,
+                    SubArgs::Badges(_) => crate::badges::write_badges(&batches()?)?,
+                    SubArgs::Perf(ArgsPerf { top }) => crate::perf::write_perf(*top)?,
+                    SubArgs::Report(ArgsReport { file }) => {
+                        let file = match file {
+                            Some(file) => file.clone(),
+                            None => self.output.join("report.html"),
+                        };
+                        crate::report::write_report(&batches()?, &file)?
+                    }
+                }
+            }
+        }
+
+        if self.clear {
+            crate::clear_batch()?;
+        }
+        Ok(())
+    }
+}
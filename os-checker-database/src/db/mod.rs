@@ -60,6 +60,11 @@ impl<'txn> LastChecks<'txn> {
         })
     }
 
+    /// The unix timestamp in milliseconds when the last check item started.
+    pub fn timestamp_start(&self) -> u64 {
+        self.checks.timestamp_start
+    }
+
     pub fn repo_counts(&self) -> usize {
         self.checks.keys.len()
     }
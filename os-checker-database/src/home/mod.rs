@@ -40,12 +40,12 @@ use serde::{Deserialize, Serialize};
 #[cfg(test)]
 mod tests;
 
-pub fn all_targets(json: &JsonOutput) -> Vec<NodeRepo> {
+pub fn all_targets(json: &JsonOutput) -> Vec<NodeRepo<'_>> {
     let data: Vec<_> = json.data.iter().collect();
     inner(json, &data)
 }
 
-pub fn split_by_target(json: &JsonOutput) -> Vec<(&str, Vec<NodeRepo>)> {
+pub fn split_by_target(json: &JsonOutput) -> Vec<(&str, Vec<NodeRepo<'_>>)> {
     let group_by_target = group_by(&json.data, |d| target_cmdidx(json, d.cmd_idx));
     let mut v = Vec::with_capacity(group_by_target.len());
 
@@ -1,10 +1,11 @@
 use camino::{Utf8Path, Utf8PathBuf};
 use itertools::Itertools;
-use os_checker_types::JsonOutput;
+use os_checker_types::{out_json::file_tree::FileTreeRepo, JsonOutput};
 use serde::Serialize;
 use std::{
     fs,
     io::{BufReader, BufWriter},
+    sync::OnceLock,
 };
 
 #[macro_use]
@@ -27,71 +28,144 @@ pub use utils::Result;
 
 mod logger;
 
+mod cli;
+
 mod targets;
 
+/// 历史检查记录中诊断数量的趋势
+mod trend;
+
 mod db;
 
+/// 增量生成 ui 数据
+mod manifest;
+
+/// 离线 HTML 报告
+mod report;
+
+/// 仓库和 package 的 SVG 徽章
+mod badges;
+
+/// 检查耗时报告
+mod perf;
+use manifest::Incremental;
+
 fn main() -> Result<()> {
     logger::init();
+    let args: cli::Args = argh::from_env();
+    args.execute()
+}
 
-    // Search all json in batch dir.
-    let paths = json_paths("batch")?;
+/// 一个 batch 的 os-checker 输出
+struct Batch {
+    /// 文件名（不含扩展名）
+    name: String,
+    json: JsonOutput,
+}
 
-    clear_base_dir()?;
+/// 读取输入目录下所有 batch JSON
+fn read_batches(dir: &Utf8Path) -> Result<Vec<Batch>> {
+    let paths = json_paths(dir.as_str())?;
+    let mut batches = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let name = path.file_stem().unwrap().to_owned();
+        batches.push(Batch {
+            name,
+            json: read_json(path)?,
+        });
+    }
+    Ok(batches)
+}
 
-    let mut pass_count_repos = stats::PassCountRepos::new();
+/// 生成所有数据
+fn generate_all(batches: &[Batch], incr: &Incremental) -> Result<()> {
+    gen_filetree(batches, incr)?;
+    gen_basic(batches, incr)?;
+    gen_home(batches)?;
+    gen_stats(batches)?;
+    badges::write_badges(batches)?;
 
-    let mut jsons = Vec::with_capacity(paths.len());
-    for path in &paths {
-        let json = read_json(path)?;
-        write_filetree(&json)?;
+    // 生成 targets 列表
+    targets::do_resolves(incr)?;
+
+    // 生成检查耗时报告
+    perf::write_perf(perf::TOP)?;
 
-        let batch = path.file_stem().unwrap();
-        write_batch_basic_home(&json, batch)?;
+    // 生成诊断数量的时间序列
+    trend::write_trend()
+}
 
-        jsons.push(json);
+/// ui/file-tree 和 ui/repos/user/repo 的 filetree 数据
+fn gen_filetree(batches: &[Batch], incr: &Incremental) -> Result<()> {
+    for batch in batches {
+        write_filetree(&batch.json, incr)?;
     }
+    Ok(())
+}
 
-    // ui/pass_count_repo/target.json
-    jsons.iter().for_each(|json| pass_count_repos.update(json));
-    pass_count_repos.write_to_file()?;
+/// ui/basic.json 和 ui/repos/user/repo/basic.json
+fn gen_basic(batches: &[Batch], incr: &Incremental) -> Result<()> {
+    let src_dir = &base_dir().join("batch/basic");
+    remove_dir(src_dir)?;
+    for batch in batches {
+        write_batch_basic(&batch.json, &batch.name, incr)?;
+    }
 
     // 把 batch config 合并
-    {
-        let src_dir = &Utf8PathBuf::from_iter([BASE_DIR, "batch", "basic"]);
-        let target_dir = &Utf8PathBuf::from_iter([BASE_DIR]);
-        if !target_dir.exists() {
-            fs::create_dir_all(target_dir)?;
-        }
-        // ui/basic.json
-        basic::write_batch(src_dir, target_dir)?;
+    let target_dir = base_dir();
+    if !target_dir.exists() {
+        fs::create_dir_all(target_dir)?;
+    }
+    basic::write_batch(src_dir, target_dir)
+}
+
+/// ui/home/split
+fn gen_home(batches: &[Batch]) -> Result<()> {
+    let home_dir = &base_dir().join("batch").join(HOME_DIR);
+    remove_dir(home_dir)?;
+    for batch in batches {
+        write_batch_home(&batch.json, &batch.name)?;
     }
 
     // 把 batch home 合并
-    {
-        let home_dir = &Utf8PathBuf::from_iter([BASE_DIR, "batch", HOME_DIR]);
-        let target_dir = &Utf8PathBuf::from_iter([BASE_DIR, HOME_DIR]);
-        if !target_dir.exists() {
-            fs::create_dir_all(target_dir)?;
-        }
-        for src_dir in subdir_paths(home_dir.as_str())? {
-            home::write_batch(&src_dir, target_dir)?;
-        }
+    let target_dir = &base_dir().join(HOME_DIR);
+    if !target_dir.exists() {
+        fs::create_dir_all(target_dir)?;
+    }
+    for src_dir in subdir_paths(home_dir.as_str())? {
+        home::write_batch(&src_dir, target_dir)?;
     }
+    Ok(())
+}
 
-    // 生成 targets 列表
-    targets::do_resolves()?;
-
-    #[cfg(feature = "clear_batch")]
-    {
-        let batch_dir = Utf8PathBuf::from_iter([BASE_DIR, "batch"]);
-        info!("正在清除 {batch_dir}");
-        fs::remove_dir_all(&batch_dir)?;
-        info!("已清除 {batch_dir}");
-        fs::remove_file(CACHE_REDB)?;
-        info!("已清除 {}", CACHE_REDB);
+/// ui/pass_count_repo/target.json
+fn gen_stats(batches: &[Batch]) -> Result<()> {
+    let mut pass_count_repos = stats::PassCountRepos::new();
+    batches
+        .iter()
+        .for_each(|batch| pass_count_repos.update(&batch.json));
+    pass_count_repos.write_to_file()
+}
+
+/// 清除输出目录中的中间数据 batch 和 redb 文件
+fn clear_batch() -> Result<()> {
+    let batch_dir = &base_dir().join("batch");
+    info!("正在清除 {batch_dir}");
+    remove_dir(batch_dir)?;
+    info!("已清除 {batch_dir}");
+
+    let db = cache_redb();
+    if db.exists() {
+        fs::remove_file(db)?;
+        info!("已清除 {db}");
     }
+    Ok(())
+}
 
+fn remove_dir(dir: &Utf8Path) -> Result<()> {
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
     Ok(())
 }
 
@@ -129,24 +203,29 @@ fn subdir_paths(dir: &str) -> Result<Vec<Utf8PathBuf>> {
         .collect_vec())
 }
 
-fn write_batch_basic_home(json: &JsonOutput, batch: &str) -> Result<()> {
-    let _span = error_span!("write_batch_basic_home", batch).entered();
+fn write_batch_basic(json: &JsonOutput, batch: &str, incr: &Incremental) -> Result<()> {
+    let _span = error_span!("write_batch_basic", batch).entered();
 
-    // Write basic JSON
     write_to_file("batch/basic", batch, &basic::all(json))?;
     for (repo, b) in basic::by_repo(json) {
+        if !incr.need_update(repo.user, repo.repo) {
+            continue;
+        }
         // 仓库的 basic 数据不参与聚合
         write_to_file(&format!("repos/{}/{}", repo.user, repo.repo), "basic", &b)?;
     }
+    Ok(())
+}
+
+fn write_batch_home(json: &JsonOutput, batch: &str) -> Result<()> {
+    let _span = error_span!("write_batch_home", batch).entered();
 
-    // Write home JSON
     let mut home = Utf8PathBuf::from_iter(["batch", HOME_DIR, ALL_TARGETS]);
     write_to_file(home.as_str(), batch, &home::all_targets(json))?;
     for (target, nodes) in home::split_by_target(json) {
         home.set_file_name(target);
         write_to_file(home.as_str(), batch, &nodes)?;
     }
-
     Ok(())
 }
 
@@ -158,25 +237,33 @@ fn read_json(path: &Utf8Path) -> Result<JsonOutput> {
 
 /// Clear old data
 fn clear_base_dir() -> Result<()> {
-    if let Err(err) = fs::remove_dir_all(BASE_DIR) {
+    let base_dir = base_dir();
+    if let Err(err) = fs::remove_dir_all(base_dir) {
         error!("{err:?}");
     }
-    info!("清理 {BASE_DIR}");
+    info!("清理 {base_dir}");
     Ok(())
 }
 
 /// 写入 filetree 和 repos 的 filetree 数据；这无需聚合
-fn write_filetree(json: &JsonOutput) -> Result<()> {
+fn write_filetree(json: &JsonOutput, incr: &Incremental) -> Result<()> {
+    let need_update = |ftree: &FileTreeRepo| incr.need_update(&ftree.repo.user, &ftree.repo.repo);
+
     let file_tree_all = file_tree::all_targets(json);
     write_to_file(FILETREE_DIR, ALL_TARGETS, &file_tree_all)?;
     for filetree in file_tree::split_by_repo(file_tree_all) {
-        write_to_file(filetree.dir().as_str(), ALL_TARGETS, &filetree)?;
+        if need_update(&filetree) {
+            write_to_file(filetree.dir().as_str(), ALL_TARGETS, &filetree)?;
+        }
     }
     for (target, filetree) in file_tree::split_by_target(json) {
         write_to_file(FILETREE_DIR, target, &filetree)?;
 
         // repo & targets
-        for ftree in file_tree::split_by_repo(filetree) {
+        for ftree in file_tree::split_by_repo(filetree)
+            .iter()
+            .filter(|f| need_update(f))
+        {
             write_to_file(ftree.dir().as_str(), target, &ftree)?;
         }
     }
@@ -188,13 +275,19 @@ fn print(t: &impl Serialize) {
     info!("{}", serde_json::to_string_pretty(t).unwrap());
 }
 
-const BASE_DIR: &str = "ui";
+/// 输出目录，由命令行参数设置，默认为 ui
+static BASE_DIR: OnceLock<Utf8PathBuf> = OnceLock::new();
+
+fn base_dir() -> &'static Utf8Path {
+    BASE_DIR.get().map_or(Utf8Path::new("ui"), |p| p.as_path())
+}
+
 const HOME_DIR: &str = "home/split"; // FIXME: 去除 split
 const FILETREE_DIR: &str = "file-tree/split"; // FIXME: 去除 split
 const ALL_TARGETS: &str = "All-Targets";
 
 fn write_to_file<T: Serialize>(dir: &str, target: &str, t: &T) -> Result<()> {
-    let mut path = Utf8PathBuf::from_iter([BASE_DIR, dir]);
+    let mut path = base_dir().join(dir);
 
     let _span = error_span!("write_to_file", ?path).entered();
 
@@ -209,4 +302,11 @@ fn write_to_file<T: Serialize>(dir: &str, target: &str, t: &T) -> Result<()> {
     Ok(())
 }
 
-pub const CACHE_REDB: &str = "cache.redb";
+/// redb 缓存文件，由命令行参数设置，默认为 cache.redb
+static CACHE_REDB: OnceLock<Utf8PathBuf> = OnceLock::new();
+
+pub fn cache_redb() -> &'static Utf8Path {
+    CACHE_REDB
+        .get()
+        .map_or(Utf8Path::new("cache.redb"), |p| p.as_path())
+}
@@ -0,0 +1,165 @@
+use crate::{base_dir, db::LastChecks, Result};
+use camino::Utf8Path;
+use os_checker_types::{db::*, Fnv1a};
+use redb::Value;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+};
+
+const MANIFEST: &str = "manifest";
+
+/// 仓库级别的目录：`ui/{dir}/{user}/{repo}`；增量生成时，只重新生成发生变化的仓库的这些目录。
+const REPO_DIRS: &[&str] = &["repos", "targets"];
+
+/// 上次生成 ui 数据时每个仓库的状态，写入 `ui/manifest.json`。
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Manifest {
+    /// os-checker-database 的版本：版本变化意味着输出格式可能变化，因此需要完全重新生成
+    version: String,
+    /// `user/repo` => 仓库状态的哈希，由 InfoKey（仓库提交与配置）和所有缓存键及其时间戳计算
+    repos: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    fn from_last_checks() -> Result<Self> {
+        let db = redb::Database::open(crate::cache_redb())?;
+        let txn = db.begin_read()?;
+        let checks = LastChecks::new(&txn)?;
+
+        let mut repos = BTreeMap::new();
+        checks.with_layout_cache(|info_key, cache_keys| {
+            let mut hasher = Fnv1a::default();
+            hasher.write(&InfoKey::as_bytes(info_key));
+            for key in cache_keys {
+                hasher.write(&CacheRepoKey::as_bytes(key));
+                let ts = checks.read_cache(key)?.unix_timestamp_milli;
+                hasher.write(&ts.to_le_bytes());
+            }
+            let [user, repo] = info_key.user_repo();
+            repos.insert(format!("{user}/{repo}"), hasher.hex());
+            Ok(())
+        })?;
+
+        Ok(Manifest {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            repos,
+        })
+    }
+
+    fn read() -> Option<Self> {
+        let path = base_dir().join(format!("{MANIFEST}.json"));
+        let bytes = fs::read(&path).ok()?;
+        match serde_json::from_slice(&bytes) {
+            Ok(manifest) => Some(manifest),
+            Err(err) => {
+                warn!(%path, ?err, "无法解析 manifest，将完全重新生成");
+                None
+            }
+        }
+    }
+}
+
+/// 决定哪些仓库的数据需要重新生成。
+pub struct Incremental {
+    /// 完全重新生成：未启用增量模式、上次的 manifest 不存在或者版本不同
+    full: bool,
+    /// 与上次相同的仓库
+    unchanged: HashSet<String>,
+    manifest: Manifest,
+}
+
+impl Incremental {
+    /// 未启用增量模式时，仍然记录本次生成的仓库状态，以便下次增量生成。
+    pub fn new(enabled: bool) -> Result<Self> {
+        let manifest = Manifest::from_last_checks()?;
+
+        let old = enabled.then(Manifest::read).flatten();
+        let (full, unchanged) = match old {
+            Some(old) if old.version == manifest.version => {
+                let unchanged = (manifest.repos.iter())
+                    .filter(|(repo, hash)| old.repos.get(*repo) == Some(hash))
+                    .map(|(repo, _)| repo.clone())
+                    .collect();
+                (false, unchanged)
+            }
+            _ => (true, HashSet::new()),
+        };
+        info!(
+            full,
+            unchanged = unchanged.len(),
+            total = manifest.repos.len(),
+            "ui 数据生成模式"
+        );
+
+        Ok(Incremental {
+            full,
+            unchanged,
+            manifest,
+        })
+    }
+
+    /// 完全重新生成，且不记录仓库状态：用于单独运行某个生成器。
+    pub fn disabled() -> Self {
+        Incremental {
+            full: true,
+            unchanged: HashSet::new(),
+            manifest: Manifest::default(),
+        }
+    }
+
+    /// 该仓库的数据是否需要重新生成
+    pub fn need_update(&self, user: &str, repo: &str) -> bool {
+        self.full || !self.unchanged.contains(&format!("{user}/{repo}"))
+    }
+
+    /// 清除旧数据：完全重新生成时清除整个 ui 目录；
+    /// 增量生成时保留未变化仓库的目录，清除其他所有数据（聚合数据总是重新生成）。
+    pub fn clear(&self) -> Result<()> {
+        if self.full {
+            crate::clear_base_dir()?;
+            return Ok(());
+        }
+
+        let base = base_dir();
+        for entry in base.read_dir_utf8()? {
+            let entry = entry?;
+            let path = entry.path();
+            if !REPO_DIRS.contains(&entry.file_name()) {
+                remove(path)?;
+                continue;
+            }
+            // ui/{dir}/{user}/{repo}
+            for user in path.read_dir_utf8()? {
+                let user = user?;
+                if !user.file_type()?.is_dir() {
+                    remove(user.path())?;
+                    continue;
+                }
+                for repo in user.path().read_dir_utf8()? {
+                    let repo = repo?;
+                    if self.need_update(user.file_name(), repo.file_name()) {
+                        remove(repo.path())?;
+                    }
+                }
+            }
+        }
+        info!("清理 {base} 中需要重新生成的数据");
+        Ok(())
+    }
+
+    /// 在所有数据生成之后写入 manifest
+    pub fn write(&self) -> Result<()> {
+        crate::write_to_file("", MANIFEST, &self.manifest)
+    }
+}
+
+fn remove(path: &Utf8Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
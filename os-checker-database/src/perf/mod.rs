@@ -0,0 +1,201 @@
+use crate::{
+    db::LastChecks,
+    utils::{new_map_with_cap, IndexMap},
+    write_to_file, Result,
+};
+use os_checker_types::CheckerTool;
+use serde::Serialize;
+use std::hash::Hash;
+
+#[cfg(test)]
+mod tests;
+
+/// 默认保留的排名数量
+pub const TOP: usize = 20;
+
+/// 检查耗时的统计
+#[derive(Debug, Default, Clone, Copy, Serialize, PartialEq)]
+struct Cost {
+    /// 所有检查命令的耗时之和（毫秒），缓存命中时使用缓存记录的耗时，即不使用缓存时的耗时
+    ms: u64,
+    /// 最近一次检查中实际运行的检查命令的耗时之和（毫秒），即未命中缓存的耗时
+    fresh_ms: u64,
+    /// 检查命令的数量
+    runs: usize,
+    /// 命中缓存的检查命令数量
+    hits: usize,
+    /// hits / runs
+    hit_ratio: f32,
+}
+
+impl Cost {
+    fn add(&mut self, ms: u64, hit: bool) {
+        self.ms += ms;
+        self.runs += 1;
+        if hit {
+            self.hits += 1;
+        } else {
+            self.fresh_ms += ms;
+        }
+        self.hit_ratio = self.hits as f32 / self.runs as f32;
+    }
+}
+
+/// 一条检查命令的耗时
+#[derive(Debug, Serialize)]
+struct Run {
+    user: String,
+    repo: String,
+    pkg: String,
+    checker: CheckerTool,
+    target: String,
+    ms: u64,
+    hit: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Ranked<K> {
+    #[serde(flatten)]
+    key: K,
+    #[serde(flatten)]
+    cost: Cost,
+}
+
+#[derive(Debug, Serialize)]
+struct Checker {
+    checker: CheckerTool,
+}
+
+#[derive(Debug, Serialize)]
+struct Repo {
+    user: String,
+    repo: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Pkg {
+    user: String,
+    repo: String,
+    pkg: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Target {
+    target: String,
+}
+
+/// 检查耗时报告：各项按 ms 降序排列，除 total 和 checkers 之外只保留前 top 项
+#[derive(Debug, Serialize)]
+struct Perf {
+    total: Cost,
+    checkers: Vec<Ranked<Checker>>,
+    repos: Vec<Ranked<Repo>>,
+    pkgs: Vec<Ranked<Pkg>>,
+    targets: Vec<Ranked<Target>>,
+    /// 最慢的检查命令
+    slowest: Vec<Run>,
+}
+
+fn rank<'a, K: Hash + Eq, T>(
+    runs: &'a [Run],
+    top: usize,
+    key: impl Fn(&'a Run) -> K,
+    to: impl Fn(K) -> T,
+) -> Vec<Ranked<T>> {
+    let mut map: IndexMap<K, Cost> = new_map_with_cap(runs.len());
+    for run in runs {
+        map.entry(key(run)).or_default().add(run.ms, run.hit);
+    }
+    let mut v: Vec<_> = map
+        .into_iter()
+        .map(|(key, cost)| Ranked { key: to(key), cost })
+        .collect();
+    v.sort_by_key(|r| std::cmp::Reverse(r.cost.ms));
+    v.truncate(top);
+    v
+}
+
+impl Perf {
+    fn new(mut runs: Vec<Run>, top: usize) -> Self {
+        let mut total = Cost::default();
+        runs.iter().for_each(|run| total.add(run.ms, run.hit));
+
+        let checkers = rank(
+            &runs,
+            usize::MAX,
+            |r| r.checker,
+            |checker| Checker { checker },
+        );
+        let repos = rank(
+            &runs,
+            top,
+            |r| (&*r.user, &*r.repo),
+            |(user, repo)| Repo {
+                user: user.into(),
+                repo: repo.into(),
+            },
+        );
+        let pkgs = rank(
+            &runs,
+            top,
+            |r| (&*r.user, &*r.repo, &*r.pkg),
+            |(user, repo, pkg)| Pkg {
+                user: user.into(),
+                repo: repo.into(),
+                pkg: pkg.into(),
+            },
+        );
+        let targets = rank(
+            &runs,
+            top,
+            |r| &*r.target,
+            |target| Target {
+                target: target.into(),
+            },
+        );
+
+        runs.sort_by_key(|r| std::cmp::Reverse(r.ms));
+        runs.truncate(top);
+
+        Perf {
+            total,
+            checkers,
+            repos,
+            pkgs,
+            targets,
+            slowest: runs,
+        }
+    }
+}
+
+/// 从最近一次检查的缓存中统计耗时，写入 `ui/perf.json`。
+///
+/// 缓存的时间戳早于本次检查开始的时间，则认为该检查命令命中缓存。
+pub fn write_perf(top: usize) -> Result<()> {
+    let db = redb::Database::open(crate::cache_redb())?;
+    let txn = db.begin_read()?;
+    let checks = LastChecks::new(&txn)?;
+    let start = checks.timestamp_start();
+
+    let mut runs = Vec::new();
+    checks.with_layout_cache(|info_key, cache_keys| {
+        let [user, repo] = info_key.user_repo();
+        for key in cache_keys {
+            let cache = checks.read_cache(key)?;
+            runs.push(Run {
+                user: user.into(),
+                repo: repo.into(),
+                pkg: key.cmd.pkg_name.to_string(),
+                checker: key.cmd.checker.checker,
+                target: key.cmd.cmd.target.clone(),
+                ms: cache.diagnostics.duration_ms,
+                hit: cache.unix_timestamp_milli < start,
+            });
+        }
+        Ok(())
+    })?;
+
+    let perf = Perf::new(runs, top);
+    info!(total = ?perf.total, "检查耗时");
+    write_to_file("", "perf", &perf)
+}
@@ -0,0 +1,48 @@
+use super::*;
+
+fn run(repo: &str, checker: CheckerTool, ms: u64, hit: bool) -> Run {
+    Run {
+        user: "user".into(),
+        repo: repo.into(),
+        pkg: repo.into(),
+        checker,
+        target: "x86_64-unknown-linux-gnu".into(),
+        ms,
+        hit,
+    }
+}
+
+#[test]
+fn rank_costs() {
+    let runs = vec![
+        run("a", CheckerTool::Clippy, 100, true),
+        run("a", CheckerTool::Lockbud, 300, false),
+        run("b", CheckerTool::Clippy, 50, false),
+        run("c", CheckerTool::Fmt, 1, false),
+    ];
+    let perf = Perf::new(runs, 2);
+
+    assert_eq!(perf.total.ms, 451);
+    assert_eq!(perf.total.fresh_ms, 351);
+    assert_eq!((perf.total.runs, perf.total.hits), (4, 1));
+
+    let checkers: Vec<_> = perf
+        .checkers
+        .iter()
+        .map(|c| (c.key.checker, c.cost.ms))
+        .collect();
+    assert_eq!(
+        checkers,
+        [
+            (CheckerTool::Lockbud, 300),
+            (CheckerTool::Clippy, 150),
+            (CheckerTool::Fmt, 1)
+        ]
+    );
+    assert_eq!(perf.checkers[1].cost.hit_ratio, 0.5);
+
+    let repos: Vec<_> = perf.repos.iter().map(|r| &*r.key.repo).collect();
+    assert_eq!(repos, ["a", "b"]);
+    let slowest: Vec<_> = perf.slowest.iter().map(|r| r.ms).collect();
+    assert_eq!(slowest, [300, 100]);
+}
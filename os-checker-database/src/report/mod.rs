@@ -0,0 +1,114 @@
+use crate::{Batch, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use os_checker_types::Kind;
+use serde::Serialize;
+use std::fs;
+
+#[cfg(test)]
+mod tests;
+
+/// 页面模板：内联 CSS 和 JS，查看时无需获取任何外部资源
+const TEMPLATE: &str = include_str!("report.html");
+/// 模板中数据的占位符
+const PLACEHOLDER: &str = "/*REPORT_DATA*/null";
+
+/// 页面所需的全部数据，由页面中的 JS 统计和渲染
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    kinds: Vec<Kind>,
+    targets: Vec<&'a str>,
+    packages: Vec<Package<'a>>,
+    diagnostics: Vec<Diagnostic<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Package<'a> {
+    user: &'a str,
+    repo: &'a str,
+    pkg: &'a str,
+    /// 被检查的 targets，包括没有诊断的 target
+    targets: Vec<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct Diagnostic<'a> {
+    /// idx referring to `packages`
+    pkg: usize,
+    target: &'a str,
+    file: &'a Utf8Path,
+    kind: Kind,
+    raw: &'a str,
+}
+
+impl<'a> Report<'a> {
+    /// 合并所有 batch：package 的索引依次偏移。
+    fn new(batches: &'a [Batch]) -> Self {
+        let mut report = Report {
+            kinds: Vec::new(),
+            targets: Vec::new(),
+            packages: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        for Batch { json, .. } in batches {
+            if report.kinds.is_empty() {
+                report.kinds = json.env.kinds.order.clone();
+            }
+
+            let offset = report.packages.len();
+            report
+                .packages
+                .extend(json.env.packages.iter().map(|p| Package {
+                    user: &p.repo.user,
+                    repo: &p.repo.repo,
+                    pkg: &p.name,
+                    targets: Vec::new(),
+                }));
+            for cmd in &json.cmd {
+                let target = &*cmd.target_triple;
+                let targets = &mut report.packages[offset + cmd.package_idx].targets;
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+                if !report.targets.contains(&target) {
+                    report.targets.push(target);
+                }
+            }
+
+            report.diagnostics.extend(json.data.iter().map(|d| {
+                let cmd = &json.cmd[d.cmd_idx];
+                Diagnostic {
+                    pkg: offset + cmd.package_idx,
+                    target: &cmd.target_triple,
+                    file: &d.file,
+                    kind: d.kind,
+                    raw: &d.raw,
+                }
+            }));
+        }
+        report.targets.sort_unstable();
+        report
+    }
+}
+
+/// 把 JSON 嵌入 `<script>`：转义 `<` 以免字符串中的 `</script>` 提前结束脚本
+fn embed_json(t: &impl Serialize) -> Result<String> {
+    Ok(serde_json::to_string(t)?.replace('<', "\\u003c"))
+}
+
+fn render(batches: &[Batch]) -> Result<String> {
+    let data = embed_json(&Report::new(batches))?;
+    Ok(TEMPLATE.replacen(PLACEHOLDER, &data, 1))
+}
+
+/// 生成单个离线 HTML 报告：仓库和 package 的汇总表、按文件展开的诊断以及 target 过滤。
+pub fn write_report(batches: &[Batch], path: &Utf8PathBuf) -> Result<()> {
+    let html = render(batches)?;
+    if let Some(parent) = path.parent() {
+        if !parent.as_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    fs::write(path, html)?;
+    info!("{path} 写入成功");
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use super::*;
+
+#[test]
+fn escape_script() -> Result<()> {
+    let raw = ["</script><script>alert(1)</script>"];
+    let json = embed_json(&raw)?;
+    assert!(!json.contains('<'));
+    let back: Vec<String> = serde_json::from_str(&json)?;
+    assert_eq!(back, raw);
+    Ok(())
+}
+
+#[test]
+fn placeholder() -> Result<()> {
+    let html = render(&[])?;
+    assert!(!html.contains(PLACEHOLDER));
+    assert!(
+        html.contains(r#"const DATA = {"kinds":[],"targets":[],"packages":[],"diagnostics":[]};"#)
+    );
+    Ok(())
+}
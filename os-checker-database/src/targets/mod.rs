@@ -1,5 +1,6 @@
 use crate::{
     db::LastChecks,
+    manifest::Incremental,
     utils::{new_map_with_cap, IndexMap},
     write_to_file, Result,
 };
@@ -40,8 +41,9 @@ impl<'a> Resolve<'a> {
     }
 }
 
-pub fn do_resolves() -> Result<()> {
-    let db = redb::Database::open(crate::CACHE_REDB)?;
+/// 仓库的 resolved 和 sources 数据只在需要时重新生成；user_repo 总是重新生成。
+pub fn do_resolves(incr: &Incremental) -> Result<()> {
+    let db = redb::Database::open(crate::cache_redb())?;
     let txn = db.begin_read()?;
 
     let checks = LastChecks::new(&txn)?;
@@ -54,6 +56,11 @@ pub fn do_resolves() -> Result<()> {
 
         let _span = error_span!("do_resolves", ?user, ?repo).entered();
 
+        if !incr.need_update(&user, &repo) {
+            v_user_repo.push((user, repo));
+            return Ok(());
+        }
+
         let layout = checks.read_layout(info_key)?;
         let CacheLayout {
             root_path,
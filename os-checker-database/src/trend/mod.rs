@@ -0,0 +1,153 @@
+use crate::{
+    utils::{new_map_with_cap, IndexMap},
+    write_to_file, Result,
+};
+use ahash::AHashMap;
+use os_checker_types::{db::*, out_json::repo_name_with_rev, Kind};
+use redb::{ReadableTable, ReadableTableMetadata, Value};
+use serde::Serialize;
+
+/// 每个 Kind 的诊断数量，按 Kind 的定义顺序排列
+type KindCounts = IndexMap<Kind, usize>;
+
+/// 某个仓库在一次检查中的诊断数量
+#[derive(Debug, Serialize)]
+struct RepoPoint {
+    /// 检查记录的索引
+    check: u32,
+    /// 检查开始的 unix 时间戳（毫秒）
+    timestamp: u64,
+    /// 被检查的提交
+    sha: String,
+    /// 提交时间的 unix 时间戳（毫秒）
+    commit_time: u64,
+    total: usize,
+    kinds: KindCounts,
+}
+
+/// 所有仓库在一次检查中的诊断数量
+#[derive(Debug, Serialize)]
+struct GlobalPoint {
+    check: u32,
+    timestamp: u64,
+    /// 被检查的仓库数量
+    repos: usize,
+    total: usize,
+    kinds: KindCounts,
+}
+
+struct RepoCounts {
+    commit_time: u64,
+    kinds: KindCounts,
+}
+
+/// 读取 CHECKS 中所有的检查记录，生成诊断数量的时间序列：
+/// * `ui/trend/global.json`
+/// * `ui/trend/repos/{user}/{repo}.json`
+///
+/// 同一个 InfoKey 可能出现在多次检查中（仓库没有新提交时复用缓存），因此对其统计结果进行缓存。
+pub fn write_trend() -> Result<()> {
+    let db = redb::Database::open(crate::cache_redb())?;
+    let txn = db.begin_read()?;
+    let checks = txn.open_table(CHECKS)?;
+    let info = txn.open_table(INFO)?;
+    let data = txn.open_table(DATA)?;
+
+    let mut counted = AHashMap::<Vec<u8>, Option<RepoCounts>>::new();
+    let mut repos = new_map_with_cap::<(String, String), Vec<RepoPoint>>(64);
+    let mut global = Vec::with_capacity(checks.len()? as usize);
+
+    for item in checks.iter()? {
+        let (idx, check) = item?;
+        let (check_idx, check) = (idx.value(), check.value());
+        let _span = error_span!("write_trend", check_idx).entered();
+
+        let mut point = GlobalPoint {
+            check: check_idx,
+            timestamp: check.timestamp_start,
+            repos: 0,
+            total: 0,
+            kinds: KindCounts::default(),
+        };
+
+        for key in &check.keys {
+            let info_key = &key.info;
+            let bytes = InfoKey::as_bytes(info_key);
+            let counts = match counted.get(&bytes) {
+                Some(counts) => counts,
+                None => {
+                    let counts = count_repo(&info, &data, info_key)?;
+                    counted.entry(bytes).or_insert(counts)
+                }
+            };
+            let Some(counts) = counts else { continue };
+
+            point.repos += 1;
+            add_counts(&mut point.kinds, &counts.kinds);
+
+            let repo = &info_key.repo;
+            let name = repo_name_with_rev(&repo.repo, info_key.config.rev());
+            let user_repo = (repo.user.to_string(), name.to_string());
+            repos.entry(user_repo).or_default().push(RepoPoint {
+                check: check_idx,
+                timestamp: check.timestamp_start,
+                sha: repo.sha.clone(),
+                commit_time: counts.commit_time,
+                total: counts.kinds.values().sum(),
+                kinds: counts.kinds.clone(),
+            });
+        }
+
+        sort_kinds(&mut point.kinds);
+        point.total = point.kinds.values().sum();
+        global.push(point);
+    }
+
+    write_to_file("trend", "global", &global)?;
+    for ((user, repo), points) in &repos {
+        write_to_file(&format!("trend/repos/{user}"), repo, points)?;
+    }
+    info!(checks = global.len(), repos = repos.len(), "trend 写入成功");
+
+    Ok(())
+}
+
+/// 统计某个仓库所有缓存的诊断数量；如果 Info 已被清除，则返回 None。
+fn count_repo(
+    info: &redb::ReadOnlyTable<InfoKey, Info>,
+    data: &redb::ReadOnlyTable<CacheRepoKey, CacheValue>,
+    info_key: &InfoKey,
+) -> Result<Option<RepoCounts>> {
+    let Some(guard) = info.get(info_key)? else {
+        warn!(?info_key, "Info key refers to none value; skip it.");
+        return Ok(None);
+    };
+    let info = guard.value();
+
+    let mut kinds = KindCounts::default();
+    for cache_key in &info.caches {
+        let Some(guard) = data.get(cache_key)? else {
+            warn!(?cache_key, "Cache key refers to none value; skip it.");
+            continue;
+        };
+        for d in &guard.value().diagnostics.data {
+            *kinds.entry(d.kind).or_default() += 1;
+        }
+    }
+    sort_kinds(&mut kinds);
+
+    Ok(Some(RepoCounts {
+        commit_time: info.latest_commit.committer.datetime,
+        kinds,
+    }))
+}
+
+fn add_counts(acc: &mut KindCounts, counts: &KindCounts) {
+    for (kind, count) in counts {
+        *acc.entry(*kind).or_default() += count;
+    }
+}
+
+fn sort_kinds(kinds: &mut KindCounts) {
+    kinds.sort_unstable_by(|a, _, b, _| (*a as u8).cmp(&(*b as u8)));
+}
@@ -33,7 +33,7 @@ impl<'a> From<UserRepo<'a>> for out_json::UserRepo {
     }
 }
 
-pub fn repo_pkgidx(json: &JsonOutput, pkg_idx: usize) -> UserRepo {
+pub fn repo_pkgidx(json: &JsonOutput, pkg_idx: usize) -> UserRepo<'_> {
     let repo = &json.env.packages[pkg_idx].repo;
     UserRepo {
         user: &repo.user,
@@ -41,12 +41,12 @@ pub fn repo_pkgidx(json: &JsonOutput, pkg_idx: usize) -> UserRepo {
     }
 }
 
-pub fn repo_cmdidx(json: &JsonOutput, cmd_idx: usize) -> UserRepo {
+pub fn repo_cmdidx(json: &JsonOutput, cmd_idx: usize) -> UserRepo<'_> {
     let pkg_idx = json.cmd[cmd_idx].package_idx;
     repo_pkgidx(json, pkg_idx)
 }
 
-pub fn pkg_cmdidx(json: &JsonOutput, cmd_idx: usize) -> UserRepoPkg {
+pub fn pkg_cmdidx(json: &JsonOutput, cmd_idx: usize) -> UserRepoPkg<'_> {
     let pkg_idx = json.cmd[cmd_idx].package_idx;
     let package_repo = &json.env.packages[pkg_idx];
     let repo = &package_repo.repo;
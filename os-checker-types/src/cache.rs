@@ -3,7 +3,9 @@ use std::hash::Hash;
 
 // 由于我们想对每个检查出了结果时缓存，而不是在仓库所有检查完成时缓存，这里需要重复数据。
 // 减少数据重复，需要新定义一个结构，在缓存和 PackagesOutputs 上。
-#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Encode, Decode, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
 pub struct CacheRepoKeyCmd {
     #[musli(with = musli::serde)]
     pub pkg_name: XString,
@@ -11,7 +13,9 @@ pub struct CacheRepoKeyCmd {
     pub cmd: CacheCmd,
 }
 
-#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Encode, Decode, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
 pub struct CacheRepoKey {
     pub repo: CacheRepo,
     pub cmd: CacheRepoKeyCmd,
@@ -28,7 +32,9 @@ redb_value!(@key CacheRepoKey, name: "OsCheckerCacheKey",
     write_err: "Cache key can't be encoded to bytes."
 );
 
-#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Encode, Decode, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
 pub struct CacheRepo {
     #[musli(with = musli::serde)]
     pub user: XString,
@@ -45,7 +51,9 @@ impl CacheRepo {
     }
 }
 
-#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Encode, Decode, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
 pub struct CacheChecker {
     pub checker: crate::CheckerTool,
     // If we don't care about the version, use None.
@@ -53,7 +61,7 @@ pub struct CacheChecker {
     pub sha: Option<String>,
 }
 
-#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+#[derive(Debug, Encode, Decode, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct CacheCmd {
     pub cmd: String,
     pub target: String,
@@ -115,7 +123,7 @@ impl Hash for CacheCmd {
     }
 }
 
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, Serialize, Deserialize)]
 pub struct OutputData {
     pub duration_ms: u64,
     pub data: Vec<OutputDataInner>,
@@ -130,15 +138,22 @@ impl fmt::Debug for OutputData {
     }
 }
 
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, Serialize, Deserialize)]
 pub struct OutputDataInner {
     #[musli(with = musli::serde)]
     pub file: Utf8PathBuf,
     pub kind: crate::Kind,
     pub raw: String,
+    /// Stable identity of the diagnostic; empty in caches written before it exists.
+    #[musli(default)]
+    #[serde(default)]
+    pub fingerprint: String,
+    #[musli(default)]
+    #[serde(default)]
+    pub detail: crate::DataDetail,
 }
 
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, Serialize, Deserialize)]
 pub struct CacheValue {
     pub unix_timestamp_milli: u64,
     pub cmd: CacheRepoKeyCmd,
@@ -1,6 +1,6 @@
 use crate::{db::InfoKey, prelude::*};
 
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, Serialize, Deserialize)]
 pub struct CheckValue {
     pub keys: Vec<Keys>,
     /// The unix timestmap in milliseconds.
@@ -66,7 +66,7 @@ redb_value!(CheckValue, name: "OsCheckerCheckValue",
     read_err: "Not a valid check value.",
     write_err: "Check value can't be encoded to bytes.");
 
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, Serialize, Deserialize)]
 pub struct Keys {
     pub info: InfoKey,
 }
@@ -17,6 +17,15 @@ pub struct RepoConfig {
     pub cmds: Cmds,
     #[musli(with = musli::serde)]
     pub packages: IndexMap<String, RepoConfig>,
+    #[musli(default, with = musli::serde)]
+    pub flags: Option<Flags>,
+}
+
+impl RepoConfig {
+    /// The branch, tag or commit sha in meta.rev.
+    pub fn rev(&self) -> Option<&str> {
+        self.meta.as_ref().and_then(|m| m.rev.as_deref())
+    }
 }
 
 #[derive(Serialize, Deserialize, Encode, Decode, Clone)]
@@ -63,6 +72,19 @@ pub struct Targets(pub MaybeMulti);
 #[derive(Debug, Serialize, Deserialize, Encode, Decode, Clone)]
 pub struct Setup(pub MaybeMulti);
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Flags {
+    pub base: FlagsBase,
+    pub target: IndexMap<String, FlagsBase>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FlagsBase {
+    pub rustflags: MaybeMulti,
+    pub lints: MaybeMulti,
+    pub profiles: MaybeMulti,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Features {
     Complete(FeaturesCompleteState),
@@ -109,6 +131,33 @@ pub struct Meta {
     pub use_last_cache: bool,
     #[serde(default = "run_all_checkers")]
     pub run_all_checkers: bool,
+    #[serde(default)]
+    #[musli(default)]
+    pub rev: Option<String>,
+    #[serde(default)]
+    #[musli(default)]
+    pub clone: Option<GitClone>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Encode, Decode, Clone, Default)]
+pub struct GitClone {
+    #[serde(default)]
+    pub depth: Option<u32>,
+    #[serde(default)]
+    pub filter: Option<String>,
+    #[serde(default)]
+    pub submodules: Submodules,
+    #[serde(default)]
+    pub sparse: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Encode, Decode, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Submodules {
+    None,
+    Shallow,
+    #[default]
+    Recursive,
 }
 
 fn empty_globs() -> MaybeMulti {
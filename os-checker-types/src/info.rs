@@ -1,6 +1,6 @@
 use crate::prelude::*;
 
-#[derive(Debug, Encode, Decode, Clone)]
+#[derive(Debug, Encode, Decode, Serialize, Deserialize, Clone)]
 pub struct InfoKey {
     pub repo: crate::db::CacheRepo,
     #[musli(with = musli::serde)]
@@ -18,7 +18,7 @@ redb_value!(@key InfoKey, name: "OsCheckerInfoKey",
     write_err: "Info key can't be encoded to bytes."
 );
 
-#[derive(Debug, Encode, Decode)]
+#[derive(Debug, Encode, Decode, Serialize, Deserialize)]
 pub struct Info {
     /// 该仓库的检查是否全部完成
     pub complete: bool,
@@ -33,7 +33,7 @@ redb_value!(Info, name: "OsCheckerInfo",
     write_err: "Info value can't be encoded to bytes."
 );
 
-#[derive(Debug, Encode, Decode)]
+#[derive(Debug, Encode, Decode, Serialize, Deserialize)]
 pub struct LatestCommit {
     pub sha: String,
     pub mes: String,
@@ -41,7 +41,7 @@ pub struct LatestCommit {
     pub committer: Committer,
 }
 
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, Serialize, Deserialize)]
 pub struct Committer {
     // store as unix timestemp milli
     pub datetime: u64,
@@ -24,7 +24,7 @@ impl CargoMetaData {
 
 pub type Workspaces = IndexMap<Utf8PathBuf, CargoMetaData>;
 
-#[derive(Encode, Decode, Default)]
+#[derive(Encode, Decode, Serialize, Deserialize, Default)]
 pub struct CacheLayout {
     /// 仓库根目录的完整路径，可用于去除 Metadata 中的路径前缀，让路径看起来更清爽
     #[musli(with = musli::serde)]
@@ -119,7 +119,7 @@ pub struct CachePackageInfo {
     pub channel: String,
 }
 
-#[derive(Debug, Encode, Decode)]
+#[derive(Debug, Encode, Decode, Serialize, Deserialize)]
 pub struct CacheResolve {
     #[musli(with = musli::serde)]
     pub pkg_name: XString,
@@ -50,6 +50,17 @@ pub struct Env {
 pub struct Tools {
     pub rust_toolchains: RustToolchains,
     pub os_checker: ToolOsChecker,
+    /// Versions of checkers that have run or been looked up in cache.
+    #[serde(default)]
+    pub checkers: Vec<ToolChecker>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ToolChecker {
+    pub checker: CheckerTool,
+    /// Empty if the checker doesn't depend on a toolchain.
+    pub toolchain: String,
+    pub version: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -118,6 +129,50 @@ pub struct Data {
     pub file: Utf8PathBuf,
     pub kind: Kind,
     pub raw: String,
+    /// Stable identity of the diagnostic across commits: unaffected by line numbers,
+    /// timestamps and checkout paths.
+    #[serde(default)]
+    pub fingerprint: String,
+    /// Structured information so that consumers needn't re-parse `raw`.
+    #[serde(flatten)]
+    pub detail: DataDetail,
+}
+
+/// Optional structured fields of a diagnostic; `raw` is still the text for display.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Encode, Decode, PartialEq, Eq)]
+pub struct DataDetail {
+    /// Primary span in `file`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+    /// Lint name, rule or advisory id, e.g. `clippy::needless_return` or `RUSTSEC-2024-0001`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// Help message or suggested fix.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+}
+
+/// 1-based line range and optional 1-based column range.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Encode, Decode, PartialEq, Eq)]
+pub struct Span {
+    pub line_start: u32,
+    pub line_end: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column_start: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column_end: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Encode, Decode, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+#[musli(name_all = "name")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
 }
 
 /// The kind a checker reports.
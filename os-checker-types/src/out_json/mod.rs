@@ -24,3 +24,11 @@ pub struct UserRepo {
     pub user: XString,
     pub repo: XString,
 }
+
+/// 输出中代表仓库的名字：指定 meta.rev 时为 `repo@rev`，以免同一仓库的不同版本在输出中相互覆盖。
+pub fn repo_name_with_rev(repo: &str, rev: Option<&str>) -> XString {
+    match rev {
+        Some(rev) => format!("{repo}@{}", rev.replace('/', "_")).into(),
+        None => repo.into(),
+    }
+}
@@ -32,6 +32,30 @@ pub fn parse_unix_timestamp_milli(ts: u64) -> OffsetDateTime {
     }
 }
 
+/// FNV-1a 64-bit hash: std's hashers are not guaranteed to be stable across releases,
+/// but some hashes are stored and compared across runs.
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv1a {
+    pub fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    /// A 16-char hex string.
+    pub fn hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
 #[macro_export]
 macro_rules! redb_value {
     (
@@ -1,11 +1,35 @@
 use crate::db::*;
 use redb::{Database, TableDefinition};
+use serde::{Deserialize, Serialize};
 
 pub const CHECKS: TableDefinition<u32, CheckValue> = TableDefinition::new("checks");
 pub const DATA: TableDefinition<CacheRepoKey, CacheValue> = TableDefinition::new("data");
 pub const INFO: TableDefinition<InfoKey, Info> = TableDefinition::new("info");
 pub const LAYOUT: TableDefinition<InfoKey, CacheLayout> = TableDefinition::new("layout");
 
+/// A row of any table: used to export and import a database as JSON lines like
+/// `{"table":"info","key":{...},"value":{...}}`.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "table", rename_all = "lowercase")]
+pub enum Record {
+    Checks {
+        key: u32,
+        value: CheckValue,
+    },
+    Data {
+        key: CacheRepoKey,
+        value: CacheValue,
+    },
+    Info {
+        key: InfoKey,
+        value: Info,
+    },
+    Layout {
+        key: InfoKey,
+        value: CacheLayout,
+    },
+}
+
 const CACHE_REDB: &str = "cache.redb";
 
 pub fn test_database(dir: &str) -> Database {
@@ -118,3 +118,47 @@ fn insert_variant_name_all() -> Result<()> {
 
     Ok(())
 }
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct S1 {
+    a: u32,
+}
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct S2 {
+    a: u32,
+    #[musli(default)]
+    b: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct VecS1 {
+    v: Vec<S1>,
+    t: u8,
+}
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct VecS2 {
+    v: Vec<S2>,
+    t: u8,
+}
+
+#[test]
+fn append_field_with_default() -> Result<()> {
+    let value = VecS1 {
+        v: vec![S1 { a: 1 }, S1 { a: 2 }],
+        t: 9,
+    };
+    let mut v = Vec::<u8>::new();
+    musli::storage::encode(&mut v, &value)?;
+
+    // NOTE: appending a field with #[musli(default)] is a non-breaking change
+    let decoded: VecS2 = musli::storage::decode(v.as_slice())?;
+    let expected = VecS2 {
+        v: vec![S2 { a: 1, b: None }, S2 { a: 2, b: None }],
+        t: 9,
+    };
+    assert_eq!(decoded, expected);
+
+    Ok(())
+}
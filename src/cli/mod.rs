@@ -1,6 +1,8 @@
 use crate::{
+    config::CheckerTool,
     config::Configs,
-    db::Db,
+    db::{Db, Query, Retention},
+    history::HistoryArgs,
     output::JsonOutput,
     run_checker::{FullOrFastOutputs, Repo, RepoOutput},
     Result,
@@ -46,11 +48,20 @@ impl Args {
             SubArgs::Run(run) => {
                 run.execute()?;
 
-                // clean repo_dir to save disk space in CI
-                let repos_dir = repos_base_dir();
-                debug!(%repos_dir, "正在清理所有下载的仓库目录");
-                std::fs::remove_dir_all(&repos_dir)?;
-                debug!(%repos_dir, "清理成功");
+                // clean repo_dir to save disk space in CI;
+                // 离线时无法重新 clone，因此保留已有的仓库
+                if !run.keep_repo && !offline() {
+                    let repos_dir = repos_base_dir();
+                    debug!(%repos_dir, "正在清理所有下载的仓库目录");
+                    std::fs::remove_dir_all(&repos_dir)?;
+                    debug!(%repos_dir, "清理成功");
+                }
+            }
+            SubArgs::History(history) => {
+                history.execute()?;
+                if !history.keep_repo {
+                    std::fs::remove_dir_all(repos_base_dir())?;
+                }
             }
             SubArgs::Batch(batch) => batch.execute()?,
             SubArgs::Config(config) => config.execute()?,
@@ -69,6 +80,7 @@ impl Args {
 
         match &self.sub_args {
             SubArgs::Run(run) => file_stem(&run.config[0]),
+            SubArgs::History(history) => file_stem(&history.config[0]),
             SubArgs::Batch(batch) => file_stem(&batch.config[0]),
             SubArgs::Layout(layout) => layout.base_dir.clone().unwrap_or_else(|| BASE_DIR.into()),
             _ => BASE_DIR.into(),
@@ -82,6 +94,7 @@ impl Args {
         let mut_config = match &mut self.sub_args {
             SubArgs::Layout(layout) => &mut layout.config,
             SubArgs::Run(run) => &mut run.config,
+            SubArgs::History(history) => &mut history.config,
             SubArgs::Batch(batch) => &mut batch.config,
             SubArgs::Config(config) => &mut config.config,
             SubArgs::Db(_) => return Ok(()),
@@ -105,6 +118,7 @@ impl Args {
 enum SubArgs {
     Layout(ArgsLayout),
     Run(ArgsRun),
+    History(ArgsHistory),
     Batch(ArgsBatch),
     Config(arg_config::ArgsConfig),
     Db(ArgsDb),
@@ -137,6 +151,15 @@ struct ArgsLayout {
     /// json output file path. Default to layout.txt.
     #[argh(option, default = "Utf8PathBuf::from(\"layout.json\")")]
     out: Utf8PathBuf,
+
+    /// a local RustSec advisory-db directory used by cargo-audit.
+    /// Default to `$CARGO_HOME/advisory-db`.
+    #[argh(option)]
+    advisory_db: Option<Utf8PathBuf>,
+
+    /// don't fetch the advisory-db, i.e. audit with the local advisory-db only
+    #[argh(switch)]
+    advisory_db_no_fetch: bool,
 }
 
 /// Run checkers on all repos.
@@ -168,6 +191,136 @@ pub struct ArgsRun {
     /// enable meta.use_last_cache for all repos
     #[argh(switch)]
     use_last_cache: bool,
+
+    /// a local RustSec advisory-db directory used by cargo-audit.
+    /// Default to `$CARGO_HOME/advisory-db`.
+    #[argh(option)]
+    advisory_db: Option<Utf8PathBuf>,
+
+    /// don't fetch the advisory-db, i.e. audit with the local advisory-db only
+    #[argh(switch)]
+    advisory_db_no_fetch: bool,
+
+    /// a dir to store patches of auto-fixable checking results: clippy's machine-applicable
+    /// suggestions in `<dir>/<user>/<repo>/<pkg>/clippy.patch`, and rustfmt's unformatted
+    /// code in `<dir>/<user>/<repo>/fmt.patch`
+    #[argh(option)]
+    patch_dir: Option<Utf8PathBuf>,
+
+    /// only check packages containing files changed since the merge base of this git rev
+    /// and HEAD (e.g. `origin/main` for a pull request), and only report diagnostics in
+    /// changed files. Results are cached, but not added to check items.
+    #[argh(option)]
+    since: Option<String>,
+
+    /// with `--since`, keep crate-wide diagnostics like audit, outdated and cargo errors
+    /// even if they're not in changed files
+    #[argh(switch)]
+    since_keep_crate_wide: bool,
+
+    /// a dir holding a separate CARGO_TARGET_DIR for each checker and toolchain, shared
+    /// by all repos to keep incremental build artifacts. Default to `.os-checker-target` in the
+    /// current dir, which survives the cleanup of the repos dir after the run.
+    #[argh(option)]
+    target_dir: Option<Utf8PathBuf>,
+
+    /// prune least recently used target dirs once their total size exceeds this many GiB.
+    /// Default to 10; 0 means never pruning.
+    #[argh(option, default = "DEFAULT_TARGET_DIR_MAX_SIZE")]
+    target_dir_max_size: u64,
+
+    /// never touch the network: use and keep existing repos in the repos dir or `file://` repos,
+    /// pass `--offline` to cargo, and skip checkers needing the network
+    #[argh(switch)]
+    offline: bool,
+}
+
+/// Check a single repo at each commit (or every Nth commit) in a range, and report the
+/// diagnostic counts per commit, e.g.
+/// `os-checker history --config a.json --from v0.1.0 --step 5 --checker clippy --db cache.redb`.
+///
+/// Results are cached per commit in the db, but not added to check items.
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "history")]
+struct ArgsHistory {
+    /// a path to json configuration file. This can be specified multiple times like
+    /// `--config a.json --config b.json`, with the merge from left to right.
+    #[argh(option)]
+    config: Vec<String>,
+
+    /// the repo key in configs; required if there are multiple repos
+    #[argh(option)]
+    repo: Option<String>,
+
+    /// the oldest commit (branch, tag or sha) to check; it's checked as the baseline
+    #[argh(option)]
+    from: String,
+
+    /// the newest commit to check. Default to HEAD, i.e. the default branch or meta.rev.
+    #[argh(option, default = "String::from(\"HEAD\")")]
+    to: String,
+
+    /// check every Nth commit; the newest commit is always checked. Default to 1.
+    #[argh(option, default = "1")]
+    step: usize,
+
+    /// only run these checkers, e.g. `--checker clippy --checker fmt`
+    #[argh(option)]
+    checker: Vec<String>,
+
+    /// a diagnostic fingerprint in the JSON output; report the commit introducing it
+    #[argh(option)]
+    fingerprint: Option<String>,
+
+    /// redb file path. If not specified, no cache for checking.
+    #[argh(option)]
+    db: Option<Utf8PathBuf>,
+
+    #[argh(option, default = "Emit::Json")]
+    /// emit the history report as JSON to stdout or a json file
+    emit: Emit,
+
+    /// keep the repo once the checks are done
+    #[argh(switch)]
+    keep_repo: bool,
+}
+
+impl ArgsHistory {
+    fn execute(&self) -> Result<()> {
+        NO_CHECK_ITEM.store(true, Ordering::SeqCst);
+
+        let configs = configurations(&self.config)?.into_inner();
+        let mut config = match &self.repo {
+            Some(repo) => configs
+                .into_iter()
+                .find(|c| c.key() == repo)
+                .with_context(|| format!("{repo} is not in configs"))?,
+            None => {
+                let keys: Vec<_> = configs.iter().map(|c| c.key().to_owned()).collect();
+                let Ok([config]) = <[_; 1]>::try_from(configs) else {
+                    bail!("Specify one of the repos by --repo: {keys:?}");
+                };
+                config
+            }
+        };
+        config.set_db(self.db.as_deref().map(Db::new).transpose()?);
+
+        let checkers = (self.checker.iter())
+            .map(|name| {
+                CheckerTool::from_str(name)
+                    .with_context(|| format!("{name} is not a valid checker name"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let args = HistoryArgs {
+            from: &self.from,
+            to: &self.to,
+            step: self.step,
+            checkers: &checkers,
+            fingerprint: self.fingerprint.as_deref(),
+        };
+        let history = crate::history::run(config, &args)?;
+        self.emit.emit(&history)
+    }
 }
 
 /// Merge configs and split it into batches.
@@ -194,16 +347,6 @@ struct ArgsBatch {
     size: usize,
 }
 
-/// Generate a JSON schema file used to validate JSON config.
-/// i.e. `{{ "$schema": "./schema.json", /* write config with JSON LSP */ }}`.
-#[derive(FromArgs, PartialEq, Debug)]
-#[argh(subcommand, name = "schema")]
-struct ArgsSchema {
-    /// path to emitted json, default to schema.json
-    #[argh(option, default = r#"Utf8PathBuf::from("schema.json")"#)]
-    path: Utf8PathBuf,
-}
-
 /// 见 `assets/JSON-data-format.md`
 #[derive(Debug, PartialEq)]
 pub enum Emit {
@@ -295,6 +438,14 @@ impl ArgsRun {
     fn execute(&self) -> Result<()> {
         NO_LAYOUT_ERROR.store(self.no_layout_error, Ordering::SeqCst);
         USE_LAST_CACHE.store(self.use_last_cache, Ordering::SeqCst);
+        OFFLINE.store(self.offline, Ordering::SeqCst);
+        let no_fetch = self.advisory_db_no_fetch || self.offline;
+        set_advisory_db(self.advisory_db.as_deref(), no_fetch)?;
+        *PATCH_DIR.lock().unwrap() = self.patch_dir.clone();
+        NO_CHECK_ITEM.store(self.since.is_some(), Ordering::SeqCst);
+        *SINCE.lock().unwrap() = self.since.clone();
+        SINCE_KEEP_CRATE_WIDE.store(self.since_keep_crate_wide, Ordering::SeqCst);
+        set_target_dir(self.target_dir.as_deref(), self.target_dir_max_size)?;
 
         let db = self.db.as_deref().map(Db::new).transpose()?;
         let start = SystemTime::now();
@@ -302,7 +453,7 @@ impl ArgsRun {
             .map(|out| {
                 let out = out?;
                 if let Either::Left(out) = &out {
-                    if !self.keep_repo {
+                    if !self.keep_repo && !offline() {
                         out.clean_repo_dir()?;
                     }
                 }
@@ -353,6 +504,7 @@ impl ArgsLayout {
         SETUP.store(false, Ordering::SeqCst);
 
         NO_LAYOUT_ERROR.store(self.no_layout_error, Ordering::SeqCst);
+        set_advisory_db(self.advisory_db.as_deref(), self.advisory_db_no_fetch)?;
 
         // FIXME: 我们需要支持 repos 为 None 的情况吗？它代表所有仓库，有意义，但没有需求。
         if self.list_targets.is_some() {
@@ -432,22 +584,179 @@ struct ArgsDb {
     /// this should be called after all checks finish
     #[argh(switch)]
     done: bool,
-    /// redb file path; this will be created if not exists
+    /// remove check items out of retention (see --keep-checks and --keep-days),
+    /// and cache no longer referenced by the remaining check items, then compact the db
+    #[argh(switch)]
+    gc: bool,
+    /// for --gc: keep the last N check items
+    #[argh(option)]
+    keep_checks: Option<usize>,
+    /// for --gc: keep check items started within N days
+    #[argh(option)]
+    keep_days: Option<u64>,
+    /// export all tables to a JSON lines file, one record per line
+    #[argh(option)]
+    export: Option<Utf8PathBuf>,
+    /// import a JSON lines file written by --export into the db
+    #[argh(option)]
+    import: Option<Utf8PathBuf>,
+    /// merge all the given db files into the one specified by --output,
+    /// e.g. `os-checker db --merge a.redb b.redb -o out.redb`
+    #[argh(switch)]
+    merge: bool,
+    /// for --merge: the output redb file path; this will be created if not exists
+    #[argh(option, short = 'o')]
+    output: Option<Utf8PathBuf>,
+    /// redb file path; this will be created if not exists.
+    /// Multiple paths are only accepted by --merge.
+    #[argh(positional)]
+    db: Vec<Utf8PathBuf>,
+    #[argh(subcommand)]
+    query: Option<ArgsDbQuery>,
+}
+
+/// Print check items, repo infos and cached results, e.g.
+/// `os-checker db query --repo os-checker --checker clippy cache.redb`.
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "query")]
+struct ArgsDbQuery {
+    /// filter by the repo owner
+    #[argh(option)]
+    user: Option<String>,
+    /// filter by the repo name, or `user/repo`
+    #[argh(option)]
+    repo: Option<String>,
+    /// filter by a prefix of the commit sha
+    #[argh(option)]
+    sha: Option<String>,
+    /// filter by the checker name like clippy
+    #[argh(option)]
+    checker: Option<String>,
+    /// filter by the package name
+    #[argh(option)]
+    pkg: Option<String>,
+    /// only show the check item at the index and the repos in it
+    #[argh(option)]
+    check: Option<u32>,
+    /// print JSON instead of tables
+    #[argh(switch)]
+    json: bool,
+    /// redb file path
     #[argh(positional)]
     db: Utf8PathBuf,
 }
 
 impl ArgsDb {
     fn execute(&self) -> Result<()> {
-        let db = Db::new(&self.db)?;
+        if let Some(query) = &self.query {
+            return query.execute();
+        }
+        if self.merge {
+            return self.merge();
+        }
+        ensure!(self.output.is_none(), "--output only works with --merge");
+        let [path] = self.db.as_slice() else {
+            bail!("Exactly one db file is expected, but got {:?}", self.db);
+        };
+        let db = Db::new(path)?;
         if self.start {
             db.new_check()
         } else if self.done {
             db.check_set_complete()
+        } else if self.gc {
+            let retention = Retention {
+                keep_checks: self.keep_checks,
+                keep_days: self.keep_days,
+            };
+            ensure!(
+                retention.keep_checks.is_some() || retention.keep_days.is_some(),
+                "--gc requires --keep-checks or --keep-days"
+            );
+            ensure!(
+                retention.keep_checks != Some(0),
+                "--keep-checks should be at least 1"
+            );
+            let stats = db.gc(retention)?;
+            info!(?stats, "gc done");
+            db.compact();
+            Ok(())
+        } else if let Some(export) = &self.export {
+            let file = std::fs::File::create(export)?;
+            let count = db.export(std::io::BufWriter::new(file))?;
+            info!(count, %export, "export done");
+            Ok(())
+        } else if let Some(import) = &self.import {
+            let file = std::fs::File::open(import)?;
+            let count = db.import(std::io::BufReader::new(file))?;
+            info!(count, %import, "import done");
+            Ok(())
         } else {
+            ensure!(
+                self.keep_checks.is_none() && self.keep_days.is_none(),
+                "--keep-checks and --keep-days only work with --gc"
+            );
             Ok(())
         }
     }
+
+    fn merge(&self) -> Result<()> {
+        let Some(output) = &self.output else {
+            bail!("--merge requires -o/--output");
+        };
+        ensure!(!self.db.is_empty(), "--merge requires db files to merge");
+        ensure!(
+            !self.db.contains(output),
+            "{output} is both an input and the output of --merge"
+        );
+        let db = Db::new(output)?;
+        for path in &self.db {
+            db.merge(path)?;
+        }
+        info!(inputs = ?self.db, %output, "merge done");
+        db.compact();
+        Ok(())
+    }
+}
+
+impl ArgsDbQuery {
+    fn execute(&self) -> Result<()> {
+        ensure!(self.db.exists(), "{} doesn't exist", self.db);
+        let (user, repo) = match self.repo.as_deref().map(|r| r.split_once('/')) {
+            Some(Some((user, repo))) => {
+                ensure!(
+                    self.user.is_none(),
+                    "--user conflicts with `--repo user/repo`"
+                );
+                (Some(user.to_owned()), Some(repo.to_owned()))
+            }
+            _ => (self.user.clone(), self.repo.clone()),
+        };
+        let checker = match self.checker.as_deref() {
+            Some(name) => Some(
+                CheckerTool::from_str(name)
+                    .with_context(|| format!("{name} is not a valid checker name"))?
+                    .into(),
+            ),
+            None => None,
+        };
+        let query = Query {
+            user,
+            repo,
+            sha: self.sha.clone(),
+            checker,
+            pkg: self.pkg.clone(),
+            check: self.check,
+        };
+
+        let output = Db::new(&self.db)?.query(&query)?;
+        if self.json {
+            serde_json::to_writer_pretty(io::stdout(), &output)?;
+            println!();
+        } else {
+            print!("{}", output.table());
+        }
+        Ok(())
+    }
 }
 
 /// Only for layout subcommand.
@@ -463,3 +772,97 @@ static USE_LAST_CACHE: AtomicBool = AtomicBool::new(false);
 pub fn use_last_cache() -> bool {
     USE_LAST_CACHE.load(Ordering::SeqCst)
 }
+
+/// run 子命令的 `--offline`：不访问网络。
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+pub fn offline() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+}
+
+/// history 子命令的检查结果只写入缓存，不加入检查记录。
+static NO_CHECK_ITEM: AtomicBool = AtomicBool::new(false);
+
+/// 是否把检查的仓库加入最新的检查记录（CHECKS 表）。
+pub fn push_check_item() -> bool {
+    !NO_CHECK_ITEM.load(Ordering::SeqCst)
+}
+
+static ADVISORY_DB: Mutex<Option<Utf8PathBuf>> = Mutex::new(None);
+static ADVISORY_DB_NO_FETCH: AtomicBool = AtomicBool::new(false);
+
+fn set_advisory_db(path: Option<&Utf8Path>, no_fetch: bool) -> Result<()> {
+    if let Some(path) = path {
+        // cargo-audit runs in workspace dirs, so make the path absolute
+        let path = absolute(path)?;
+        if no_fetch {
+            ensure!(path.exists(), "advisory-db {path} doesn't exist");
+        }
+        *ADVISORY_DB.lock().unwrap() = Some(path);
+    }
+    ADVISORY_DB_NO_FETCH.store(no_fetch, Ordering::SeqCst);
+    Ok(())
+}
+
+/// The local advisory-db dir specified by `--advisory-db`.
+pub fn advisory_db() -> Option<Utf8PathBuf> {
+    ADVISORY_DB.lock().unwrap().clone()
+}
+
+pub fn advisory_db_no_fetch() -> bool {
+    ADVISORY_DB_NO_FETCH.load(Ordering::SeqCst)
+}
+
+static PATCH_DIR: Mutex<Option<Utf8PathBuf>> = Mutex::new(None);
+
+/// The dir specified by `--patch-dir`. None means no patch is generated.
+pub fn patch_dir() -> Option<Utf8PathBuf> {
+    PATCH_DIR.lock().unwrap().clone()
+}
+
+static SINCE: Mutex<Option<String>> = Mutex::new(None);
+static SINCE_KEEP_CRATE_WIDE: AtomicBool = AtomicBool::new(false);
+
+/// The git rev specified by `--since`. None means checking all packages.
+pub fn since() -> Option<String> {
+    SINCE.lock().unwrap().clone()
+}
+
+pub fn since_keep_crate_wide() -> bool {
+    SINCE_KEEP_CRATE_WIDE.load(Ordering::SeqCst)
+}
+
+fn absolute(path: &Utf8Path) -> Result<Utf8PathBuf> {
+    let path = std::path::absolute(path)?;
+    Utf8PathBuf::from_path_buf(path).map_err(|p| eyre::eyre!("{p:?} is not a UTF-8 path"))
+}
+
+static TARGET_DIR: Mutex<Option<Utf8PathBuf>> = Mutex::new(None);
+/// In GiB. Build artifacts grow quickly across repos, so they're pruned by default.
+const DEFAULT_TARGET_DIR_MAX_SIZE: u64 = 10;
+static TARGET_DIR_MAX_SIZE: Mutex<Option<u64>> =
+    Mutex::new(Some(DEFAULT_TARGET_DIR_MAX_SIZE << 30));
+
+fn set_target_dir(path: Option<&Utf8Path>, max_size_gib: u64) -> Result<()> {
+    // checkers run in pkg dirs, so make the path absolute
+    *TARGET_DIR.lock().unwrap() = path.map(absolute).transpose()?;
+    *TARGET_DIR_MAX_SIZE.lock().unwrap() = (max_size_gib != 0).then_some(max_size_gib << 30);
+    Ok(())
+}
+
+/// The dir specified by `--target-dir`, default to `.os-checker-target` in the current dir.
+/// It's not in the repos dir, because the repos dir is removed after `run`.
+/// Each checker and toolchain has its own CARGO_TARGET_DIR in it.
+pub fn target_dir_root() -> Utf8PathBuf {
+    let dir = TARGET_DIR.lock().unwrap().clone();
+    dir.unwrap_or_else(|| {
+        let dir = Utf8PathBuf::from(".os-checker-target");
+        absolute(&dir).unwrap_or(dir)
+    })
+}
+
+/// The max size in bytes specified by `--target-dir-max-size`, default to 10 GiB.
+/// None means no pruning.
+pub fn target_dir_max_size() -> Option<u64> {
+    *TARGET_DIR_MAX_SIZE.lock().unwrap()
+}
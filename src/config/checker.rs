@@ -1,4 +1,3 @@
-use camino::Utf8Path;
 use musli::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use CheckerTool::*;
@@ -68,17 +67,4 @@ impl CheckerTool {
             _ => return None,
         })
     }
-
-    /// To reduce outdated artifacts of other checkers,
-    /// call cargo clean before some checkers start.
-    pub fn cargo_clean(self, workspace_dirs: &[&Utf8Path]) {
-        if matches!(self, Mirai | Rapx | Geiger) {
-            let clean = &duct::cmd!("cargo", "clean");
-            for dir in workspace_dirs {
-                if let Err(err) = clean.clone().dir(dir).run() {
-                    error!(?self, %dir, ?err, "Failed to call cargo clean.");
-                }
-            }
-        }
-    }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    cli::offline,
     config::{CheckerTool, Resolve},
     layout::Pkg,
     output::host_toolchain,
@@ -12,8 +13,19 @@ use duct::{cmd, Expression};
 use indexmap::IndexMap;
 use yash_syntax::syntax::{SimpleCommand, Unquote, Value};
 
+/// `run --offline`：CARGO_NET_OFFLINE 等同于 `cargo --offline`，并且也作用于 cargo 子命令
+/// （比如 lockbud）内部调用的 cargo；它不改变检查命令字符串，因此离线与否共享检查缓存。
+fn set_offline(expr: Expression) -> Expression {
+    if offline() {
+        expr.env("CARGO_NET_OFFLINE", "true")
+    } else {
+        expr
+    }
+}
+
 fn add_env(mut expr: Expression, env: &IndexMap<String, String>) -> (Expression, String) {
     use std::fmt::Write;
+    expr = set_offline(expr);
     let mut env_str = String::new();
     for (name, val) in env {
         expr = expr.env(name, val);
@@ -53,24 +65,31 @@ pub fn cargo_clippy(pkg: &Pkg) -> Resolve {
         "--message-format=json",
     ];
     args.extend(pkg.features_args.iter().map(|s| &**s));
+    let mut flags_str = String::new();
+    if !pkg.flags.is_empty() {
+        // 参数传给 clippy-driver，因此只作用于该 package，而不作用于依赖
+        args.push("--");
+        args.extend(pkg.flags.iter().map(|s| &**s));
+        flags_str = format!(" -- {}", pkg.flags.join(" "));
+    }
     let expr = cmd("cargo", args).dir(pkg.dir);
     let (expr, env_str) = add_env(expr, &pkg.env);
     debug!(?expr);
     let cmd = format!(
-        "{env_str}cargo clippy --target {} {} --no-deps",
+        "{env_str}cargo clippy --target {} {} --no-deps{flags_str}",
         pkg.target,
         pkg.features_args.join(" ")
     );
-    Resolve::new(pkg, CheckerTool::Clippy, cmd, expr)
+    let mut resolve = Resolve::new(pkg, CheckerTool::Clippy, cmd, expr);
+    resolve.flags = pkg.flags.iter().map(|s| s.into()).collect();
+    resolve
 }
 
 /// 默认运行 cargo lockbud 的命令
 pub fn cargo_lockbud(pkg: &Pkg) -> Resolve {
-    // // 由于 cargo build 进行增量编译时，不输出旧 MIR，
-    // // lockbud 无法检查。因此要么不增量编译，要么 cargo clean，要么
-    // // 单独放置编译目录放置来不影响别的检查的增量编译。
-    // let mut lockbud_dir = pkg.dir.to_owned();
-    // lockbud_dir.extend(["__lockbud__", pkg.target]);
+    // 由于 cargo build 进行增量编译时，不输出旧 MIR，lockbud 无法检查。
+    // 因此 lockbud 使用单独的 CARGO_TARGET_DIR 来不影响别的检查的增量编译，
+    // 并在运行前清除该 package 的编译产物：见 run_checker::target_dir。
 
     let mut args = vec![
         PLUS_TOOLCHAIN_LOCKBUD,
@@ -203,12 +222,13 @@ pub fn cargo_outdated(pkg: &Pkg) -> Resolve {
         "outdated",
         "-R",
         "--exit-code=2",
-        "--color=never"
+        "--color=never",
+        "--format=json"
     )
     .dir(pkg.dir);
     let (expr, env_str) = add_env(expr, &pkg.env);
     debug!(?expr);
-    let cmd = format!("{env_str}cargo {toolchain} outdated -R --exit-code=2");
+    let cmd = format!("{env_str}cargo {toolchain} outdated -R --exit-code=2 --format=json");
     Resolve::new(pkg, CheckerTool::Outdated, cmd, expr)
 }
 
@@ -235,7 +255,14 @@ pub fn cargo_semver_checks(pkg: &Pkg) -> Resolve {
 
 pub fn cargo_udeps(pkg: &Pkg) -> Resolve {
     let toolchain = host_toolchain();
-    let mut args = vec![&toolchain, "udeps", "--color=never", "--target", pkg.target];
+    let mut args = vec![
+        &toolchain,
+        "udeps",
+        "--color=never",
+        "--output=json",
+        "--target",
+        pkg.target,
+    ];
     args.extend(pkg.features_args.iter().map(|s| &**s));
 
     let expr = cmd("cargo", args).dir(pkg.dir);
@@ -243,7 +270,7 @@ pub fn cargo_udeps(pkg: &Pkg) -> Resolve {
     let (expr, env_str) = add_env(expr, &pkg.env);
     debug!(?expr);
     let cmd = format!(
-        "{env_str}cargo {toolchain} udeps --target {} {}",
+        "{env_str}cargo {toolchain} udeps --output=json --target {} {}",
         pkg.target,
         pkg.features_args.join(" ")
     );
@@ -262,7 +289,7 @@ pub fn custom(line: &str, pkg: &Pkg, checker: CheckerTool) -> Result<Resolve> {
 
     // 构造命令、设置工作目录
     let exe = words.remove(0);
-    let mut expr = cmd(exe, words).dir(pkg.dir);
+    let mut expr = set_offline(cmd(exe, words).dir(pkg.dir));
 
     // 设置环境变量
     debug!(assigns.len = input.assigns.len());
@@ -67,6 +67,7 @@ fn custom_cmd() {
         features_args: vec![],
         toolchain: Some(0),
         env: Default::default(),
+        flags: vec![],
         audit: None,
         is_lib: true,
     };
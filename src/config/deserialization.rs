@@ -2,6 +2,7 @@ use super::checker::CheckerTool;
 use crate::{
     config::{checker::TOOLS, Resolve},
     layout::{PackageInfoShared, Packages, Pkg},
+    utils::CloneOptions,
     Result,
 };
 use eyre::Context;
@@ -11,7 +12,7 @@ use std::fmt::Debug;
 
 mod config_options;
 use config_options::{Cmds, Meta, Targets};
-pub use config_options::{Features, Setup, TargetEnv};
+pub use config_options::{Features, Flags, Setup, Submodules, TargetEnv};
 
 mod misc;
 pub use misc::TargetsSpecifed;
@@ -48,6 +49,10 @@ pub struct RepoConfig {
     #[serde(default)]
     #[serde(skip_serializing_if = "IndexMap::is_empty")]
     pub packages: IndexMap<String, RepoConfig>,
+
+    /// 附加到 clippy 的 rustc flags 和 lint 参数：repo 与 package 的参数会依次合并
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<Flags>,
 }
 
 impl RepoConfig {
@@ -91,11 +96,15 @@ impl RepoConfig {
                 .and_then(|config| config.features.as_deref())
                 .unwrap_or_default();
 
+            let pkg_flags = self.packages.get(pkg_name).and_then(|c| c.flags.as_ref());
+            let flags: Vec<_> = self.flags.iter().chain(pkg_flags).collect();
+
             // if targets is empty, pick candidates detected from repo
             let pkgs = info.pkgs(
                 pkg_name,
                 targets,
                 features,
+                &flags,
                 self.env.as_ref(),
                 self.meta.as_ref().map(|m| &m.target_env),
             )?;
@@ -192,6 +201,22 @@ impl RepoConfig {
         Ok(())
     }
 
+    pub fn validate_flags(&self, repo: &str) -> Result<()> {
+        if let Some(flags) = &self.flags {
+            flags
+                .validate()
+                .with_context(|| format!("{repo:?}'s flags value is invalid."))?;
+        }
+        for (pkg_name, pkg_config) in &self.packages {
+            if let Some(flags) = &pkg_config.flags {
+                flags.validate().with_context(|| {
+                    format!("The flags value of {repo:?}'s pkg `{pkg_name}` is invalid.")
+                })?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn validate_meta(&self, repo: &str) -> Result<()> {
         if let Some(meta) = &self.meta {
             meta.check_skip_pkg_dir_globs()
@@ -202,6 +227,16 @@ impl RepoConfig {
                 !(meta.rerun && meta.use_last_cache),
                 "meta.rerun and meta.use_last_cache can't be both true in {repo:?}"
             );
+            meta.check_clone()
+                .with_context(|| format!("{repo:?}'s meta.clone value is invalid."))?;
+            if let Some(rev) = &meta.rev {
+                ensure!(!rev.trim().is_empty(), "meta.rev is empty in {repo:?}");
+                ensure!(
+                    !repo.starts_with("file://"),
+                    "meta.rev is not supported for the local repo {repo:?}; \
+                     check out the revision in the local directory instead"
+                );
+            }
         }
         Ok(())
     }
@@ -232,6 +267,37 @@ impl RepoConfig {
         self.packages.sort_unstable_keys();
     }
 
+    /// The branch, tag or commit sha to be checked; None means the default branch.
+    pub fn rev(&self) -> Option<&str> {
+        self.meta.as_ref().and_then(|m| m.rev.as_deref())
+    }
+
+    pub fn clone_options(&self) -> CloneOptions {
+        self.meta
+            .as_ref()
+            .map(|m| m.clone_options())
+            .unwrap_or_default()
+    }
+
+    /// 检查某个提交：meta.use_last_cache 不区分提交，因此禁用它。
+    pub fn set_checkout(&mut self, sha: &str) {
+        let meta = self.meta.get_or_insert_with(Meta::default);
+        meta.rev = Some(sha.to_owned());
+        meta.use_last_cache = false;
+    }
+
+    /// 禁用其他检查工具，并启用选中的检查工具（保留自定义命令）。
+    pub fn select_checkers(&mut self, checkers: &[CheckerTool]) {
+        // 补全所有检查工具，从而覆盖 run_all_checkers 的默认值
+        let mut cmds = Cmds::new_with_all_checkers_enabled(true);
+        cmds.merge(&self.cmds);
+        self.cmds = cmds;
+        self.cmds.select(checkers);
+        for pkg in self.packages.values_mut() {
+            pkg.cmds.select(checkers);
+        }
+    }
+
     /// Get data from meta field.
     /// Directly returns None value if meta is None.
     pub fn get_meta<T>(&self, f: impl FnOnce(&Meta) -> T) -> Option<T> {
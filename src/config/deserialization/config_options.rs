@@ -1,10 +1,14 @@
 use super::*;
+use crate::utils::{sparse_patterns, CloneOptions};
 use eyre::Context;
 use CheckerTool::*;
 
 mod features;
 pub use self::features::Features;
 
+mod flags;
+pub use self::flags::Flags;
+
 mod type_conversion;
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -67,6 +71,16 @@ impl MaybeMulti {
             MaybeMulti::Multi(v) => v,
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.as_slice().is_empty()
+    }
+}
+
+impl Default for MaybeMulti {
+    fn default() -> Self {
+        MaybeMulti::Multi(Vec::new())
+    }
 }
 
 impl Debug for MaybeMulti {
@@ -166,6 +180,17 @@ impl Cmds {
     pub fn is_empty(&self) -> bool {
         self.map.is_empty()
     }
+
+    /// 禁用未选中的检查工具；选中的检查工具如果被禁用则启用。只修改 map 中已有的项。
+    pub fn select(&mut self, checkers: &[CheckerTool]) {
+        for (checker, cmd) in &mut self.map {
+            if !checkers.contains(checker) {
+                *cmd = DISABLE;
+            } else if matches!(cmd, EnableOrCustom::Enable(false)) {
+                *cmd = ENABLED;
+            }
+        }
+    }
 }
 
 impl std::ops::Deref for Cmds {
@@ -202,6 +227,44 @@ pub struct Meta {
 
     #[serde(default = "run_all_checkers")]
     pub run_all_checkers: bool,
+
+    /// 检查的分支、标签或者提交 sha；未指定时检查默认分支
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+
+    /// 浅克隆、部分克隆、子模块和 sparse checkout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clone: Option<GitClone>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GitClone {
+    /// `git clone --depth`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u32>,
+
+    /// `git clone --filter`，比如 `blob:none`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+
+    #[serde(default)]
+    pub submodules: Submodules,
+
+    /// 只检出 only_pkg_dir_globs 匹配的目录
+    #[serde(default)]
+    pub sparse: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Submodules {
+    /// 不拉取子模块
+    None,
+    /// 递归拉取子模块，但只拉取最新的一个提交
+    Shallow,
+    /// 递归拉取子模块的完整历史
+    #[default]
+    Recursive,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -255,6 +318,37 @@ impl Meta {
             .collect()
     }
 
+    pub fn clone_options(&self) -> CloneOptions {
+        let Some(clone) = &self.clone else {
+            return CloneOptions::default();
+        };
+        CloneOptions {
+            depth: clone.depth,
+            filter: clone.filter.clone(),
+            submodules: clone.submodules,
+            sparse: if clone.sparse {
+                sparse_patterns(self.only_pkg_dir_globs.as_slice())
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    pub fn check_clone(&self) -> Result<()> {
+        let Some(clone) = &self.clone else {
+            return Ok(());
+        };
+        ensure!(clone.depth != Some(0), "clone.depth must be positive");
+        if let Some(filter) = &clone.filter {
+            ensure!(!filter.trim().is_empty(), "clone.filter is empty");
+        }
+        ensure!(
+            !clone.sparse || !self.only_pkg_dir_globs.is_empty(),
+            "clone.sparse requires meta.only_pkg_dir_globs"
+        );
+        Ok(())
+    }
+
     pub fn check_skip_pkg_dir_globs(&self) -> Result<()> {
         for s in self.skip_pkg_dir_globs.as_slice() {
             glob_pattern(s)?;
@@ -284,6 +378,8 @@ impl Default for Meta {
             rerun: false,
             use_last_cache: false,
             run_all_checkers: run_all_checkers(),
+            rev: None,
+            clone: None,
         }
     }
 }
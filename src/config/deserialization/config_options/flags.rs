@@ -0,0 +1,147 @@
+use super::MaybeMulti;
+use crate::Result;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+mod type_conversion;
+
+/// 附加到 clippy 检查上的 rustc flags 和 lint 参数：可用于 repo、package 和 target。
+///
+/// 这些参数放在 `cargo clippy -- <flags>` 中，所以只作用于被检查的 package，
+/// 不会重新编译依赖，也不会覆盖 `.cargo/config.toml` 中的 rustflags（而 RUSTFLAGS 会）。
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Flags {
+    #[serde(flatten)]
+    base: FlagsBase,
+    /// { "target1": { "rustflags": "--cfg=xxx" } }
+    #[serde(default)]
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    target: IndexMap<String, FlagsBase>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FlagsBase {
+    /// rustc flags like `--cfg=kernel` or `-Zcrate-attr=feature(xxx)`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "MaybeMulti::is_empty")]
+    rustflags: MaybeMulti,
+    /// clippy lint arguments like `-D clippy::unwrap_used`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "MaybeMulti::is_empty")]
+    lints: MaybeMulti,
+    /// Builtin lint profiles, see `PROFILES`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "MaybeMulti::is_empty")]
+    profiles: MaybeMulti,
+}
+
+/// 内置的 lint 配置：名称与 lint 参数
+pub const PROFILES: &[(&str, &[&str])] = &[
+    (
+        "kernel-strict",
+        &[
+            "-W",
+            "clippy::pedantic",
+            "-W",
+            "clippy::undocumented_unsafe_blocks",
+            "-W",
+            "clippy::missing_safety_doc",
+            "-W",
+            "clippy::multiple_unsafe_ops_per_block",
+        ],
+    ),
+    ("pedantic", &["-W", "clippy::pedantic"]),
+    ("nursery", &["-W", "clippy::nursery"]),
+    (
+        "no-panic",
+        &[
+            "-W",
+            "clippy::unwrap_used",
+            "-W",
+            "clippy::expect_used",
+            "-W",
+            "clippy::panic",
+        ],
+    ),
+];
+
+fn profile(name: &str) -> Option<&'static [&'static str]> {
+    PROFILES.iter().find(|(p, _)| *p == name).map(|(_, v)| *v)
+}
+
+impl FlagsBase {
+    fn validate(&self) -> Result<()> {
+        for name in self.profiles.as_slice() {
+            ensure!(
+                profile(name).is_some(),
+                "Lint profile `{name}` is unknown; available profiles are {:?}",
+                PROFILES.iter().map(|(p, _)| *p).collect::<Vec<_>>()
+            );
+        }
+        Ok(())
+    }
+
+    /// rustflags 在前，然后是 profiles，最后是 lints，以便自定义的 lints 覆盖 profiles。
+    /// 每个元素按空白字符切分，因此 `"-W clippy::pedantic"` 与 `["-W", "clippy::pedantic"]` 等价。
+    fn push_args(&self, args: &mut Vec<String>) {
+        let split = |v: &MaybeMulti| {
+            v.as_slice()
+                .iter()
+                .flat_map(|s| s.split_whitespace().map(String::from))
+                .collect::<Vec<_>>()
+        };
+        args.extend(split(&self.rustflags));
+        for name in self.profiles.as_slice() {
+            let lints = profile(name).unwrap_or_default();
+            args.extend(lints.iter().map(|s| (*s).to_owned()));
+        }
+        args.extend(split(&self.lints));
+    }
+}
+
+impl Flags {
+    pub fn validate(&self) -> Result<()> {
+        self.base.validate()?;
+        for base in self.target.values() {
+            base.validate()?;
+        }
+        Ok(())
+    }
+
+    /// 按 repo => package 的顺序合并参数，每一级中通用参数在前，target 参数在后。
+    pub fn args<'a>(flags: impl IntoIterator<Item = &'a Flags>, target: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        for flags in flags {
+            flags.base.push_args(&mut args);
+            if let Some(base) = flags.target.get(target) {
+                base.push_args(&mut args);
+            }
+        }
+        args
+    }
+}
+
+#[test]
+fn merge_flags() -> Result<()> {
+    let repo: Flags = serde_json::from_str(
+        r#"{
+  "rustflags": "--cfg=kernel",
+  "profiles": "kernel-strict",
+  "target": { "riscv64gc-unknown-none-elf": { "rustflags": ["--cfg", "riscv"] } }
+}"#,
+    )?;
+    let pkg: Flags = serde_json::from_str(r#"{ "lints": "-A clippy::missing_safety_doc" }"#)?;
+    repo.validate()?;
+    pkg.validate()?;
+
+    let riscv = Flags::args([&repo, &pkg], "riscv64gc-unknown-none-elf").join(" ");
+    expect_test::expect!["--cfg=kernel -W clippy::pedantic -W clippy::undocumented_unsafe_blocks -W clippy::missing_safety_doc -W clippy::multiple_unsafe_ops_per_block --cfg riscv -A clippy::missing_safety_doc"]
+    .assert_eq(&riscv);
+    let x86 = Flags::args([&pkg], "x86_64-unknown-linux-gnu").join(" ");
+    expect_test::expect!["-A clippy::missing_safety_doc"].assert_eq(&x86);
+
+    let unknown: Flags = serde_json::from_str(r#"{ "profiles": ["strict"] }"#)?;
+    expect_test::expect![[r#"Lint profile `strict` is unknown; available profiles are ["kernel-strict", "pedantic", "nursery", "no-panic"]"#]]
+    .assert_eq(&unknown.validate().unwrap_err().to_string());
+    Ok(())
+}
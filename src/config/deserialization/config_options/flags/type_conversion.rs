@@ -0,0 +1,56 @@
+use super::{Flags, FlagsBase};
+use os_checker_types::config as out;
+
+// ********** CLI => os_checker_types **********
+
+impl From<Flags> for out::Flags {
+    fn from(Flags { base, target }: Flags) -> Self {
+        Self {
+            base: base.into(),
+            target: target.into_iter().map(|(k, v)| (k, v.into())).collect(),
+        }
+    }
+}
+
+impl From<FlagsBase> for out::FlagsBase {
+    fn from(
+        FlagsBase {
+            rustflags,
+            lints,
+            profiles,
+        }: FlagsBase,
+    ) -> Self {
+        Self {
+            rustflags: rustflags.into(),
+            lints: lints.into(),
+            profiles: profiles.into(),
+        }
+    }
+}
+
+// ********** os_checker_types => CLI **********
+
+impl From<out::Flags> for Flags {
+    fn from(out::Flags { base, target }: out::Flags) -> Self {
+        Self {
+            base: base.into(),
+            target: target.into_iter().map(|(k, v)| (k, v.into())).collect(),
+        }
+    }
+}
+
+impl From<out::FlagsBase> for FlagsBase {
+    fn from(
+        out::FlagsBase {
+            rustflags,
+            lints,
+            profiles,
+        }: out::FlagsBase,
+    ) -> Self {
+        Self {
+            rustflags: rustflags.into(),
+            lints: lints.into(),
+            profiles: profiles.into(),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Cmds, Env, Meta, Setup, TargetEnv, Targets};
+use super::{Cmds, Env, GitClone, Meta, Setup, Submodules, TargetEnv, Targets};
 use os_checker_types::config as out;
 
 // ********** CLI => os_checker_types **********
@@ -36,6 +36,8 @@ impl From<Meta> for out::Meta {
             rerun,
             use_last_cache,
             run_all_checkers,
+            rev,
+            clone,
         } = value;
         Self {
             only_pkg_dir_globs: only_pkg_dir_globs.into(),
@@ -44,6 +46,35 @@ impl From<Meta> for out::Meta {
             rerun,
             use_last_cache,
             run_all_checkers,
+            rev,
+            clone: clone.map(|c| c.into()),
+        }
+    }
+}
+
+impl From<GitClone> for out::GitClone {
+    fn from(value: GitClone) -> Self {
+        let GitClone {
+            depth,
+            filter,
+            submodules,
+            sparse,
+        } = value;
+        Self {
+            depth,
+            filter,
+            submodules: submodules.into(),
+            sparse,
+        }
+    }
+}
+
+impl From<Submodules> for out::Submodules {
+    fn from(value: Submodules) -> Self {
+        match value {
+            Submodules::None => Self::None,
+            Submodules::Shallow => Self::Shallow,
+            Submodules::Recursive => Self::Recursive,
         }
     }
 }
@@ -97,6 +128,8 @@ impl From<out::Meta> for Meta {
             rerun,
             use_last_cache,
             run_all_checkers,
+            rev,
+            clone,
         } = value;
         Self {
             only_pkg_dir_globs: only_pkg_dir_globs.into(),
@@ -105,6 +138,35 @@ impl From<out::Meta> for Meta {
             rerun,
             use_last_cache,
             run_all_checkers,
+            rev,
+            clone: clone.map(|c| c.into()),
+        }
+    }
+}
+
+impl From<out::GitClone> for GitClone {
+    fn from(value: out::GitClone) -> Self {
+        let out::GitClone {
+            depth,
+            filter,
+            submodules,
+            sparse,
+        } = value;
+        Self {
+            depth,
+            filter,
+            submodules: submodules.into(),
+            sparse,
+        }
+    }
+}
+
+impl From<out::Submodules> for Submodules {
+    fn from(value: out::Submodules) -> Self {
+        match value {
+            out::Submodules::None => Self::None,
+            out::Submodules::Shallow => Self::Shallow,
+            out::Submodules::Recursive => Self::Recursive,
         }
     }
 }
@@ -17,6 +17,7 @@ impl From<RepoConfig> for out::RepoConfig {
             env,
             cmds,
             packages,
+            flags,
         } = value;
         Self {
             meta: meta.map(|m| m.into()),
@@ -27,6 +28,7 @@ impl From<RepoConfig> for out::RepoConfig {
             env,
             cmds: cmds.into(),
             packages: packages.into_iter().map(|(k, v)| (k, v.into())).collect(),
+            flags: flags.map(|f| f.into()),
         }
     }
 }
@@ -72,6 +74,7 @@ impl From<out::RepoConfig> for RepoConfig {
             env,
             cmds,
             packages,
+            flags,
         } = value;
         Self {
             meta: meta.map(|m| m.into()),
@@ -82,6 +85,7 @@ impl From<out::RepoConfig> for RepoConfig {
             env,
             cmds: cmds.into(),
             packages: packages.into_iter().map(|(k, v)| (k, v.into())).collect(),
+            flags: flags.map(|f| f.into()),
         }
     }
 }
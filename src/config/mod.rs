@@ -2,7 +2,7 @@ use crate::{
     cli::use_last_cache,
     db::{get_info, Db, InfoKeyValue, RcCachedInfoKeyValue},
     layout::Packages,
-    Result,
+    Result, XString,
 };
 use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
 use color_eyre::owo_colors::OwoColorize;
@@ -10,7 +10,7 @@ use either::Either;
 use eyre::Context;
 use indexmap::IndexSet;
 use itertools::Itertools;
-use os_checker_types::db::ListTargets;
+use os_checker_types::{db::ListTargets, out_json::repo_name_with_rev};
 use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
 use serde_json::Value;
 
@@ -27,13 +27,15 @@ mod checker;
 pub use checker::{CheckerTool, TOOLS};
 
 mod deserialization;
-pub use deserialization::{Features, RepoConfig, Setup, TargetEnv, TargetsSpecifed};
+pub use deserialization::{
+    Features, Flags, RepoConfig, Setup, Submodules, TargetEnv, TargetsSpecifed,
+};
 
 #[cfg(test)]
 mod tests;
 
 /// A repo and its checker configurations.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(try_from = "serde_json::Value")]
 pub struct Config {
     uri: uri::Uri,
@@ -44,7 +46,8 @@ pub struct Config {
 impl Config {
     /// 获取该代码库的本地路径：如果指定 Github 或者 Url，则调用 git clone 命令下载
     pub fn local_root_path_with_git_clone(&mut self) -> Result<Utf8PathBuf> {
-        self.uri.local_root_path_with_git_clone()
+        let opts = self.config.clone_options();
+        self.uri.local_root_path_with_git_clone(&opts)
     }
 
     pub fn repo_name(&self) -> &str {
@@ -55,6 +58,35 @@ impl Config {
         self.uri.user_name()
     }
 
+    /// The branch, tag or commit sha to be checked; None means the default branch.
+    pub fn rev(&self) -> Option<&str> {
+        self.config.rev()
+    }
+
+    /// 输出中代表该仓库的名字：指定 meta.rev 时为 `repo@rev`。
+    pub fn output_repo_name(&self) -> XString {
+        repo_name_with_rev(self.repo_name(), self.rev())
+    }
+
+    /// 检出并检查某个提交，但仍然使用原来的本地目录。
+    pub fn set_checkout(&mut self, sha: &str) {
+        self.uri.set_checkout(sha);
+        self.config.set_checkout(sha);
+    }
+
+    /// 只运行这些检查工具。
+    pub fn select_checkers(&mut self, checkers: &[CheckerTool]) {
+        self.config.select_checkers(checkers);
+    }
+
+    pub fn key(&self) -> &str {
+        self.uri.key()
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.uri.local_source().is_some()
+    }
+
     pub fn is_in_repos(&self, repos: &[&str]) -> bool {
         let key = self.uri.key();
         for &repo in repos {
@@ -78,7 +110,8 @@ impl Config {
         if self.use_last_cache() || use_last_cache() {
             info!("{}", "Try to get last cache.".yellow());
             if let Some(db) = self.db() {
-                let opt = db.get_cached_info_key_and_value(self.user_name(), self.repo_name())?;
+                let repo = self.output_repo_name();
+                let opt = db.get_cached_info_key_and_value(self.user_name(), &repo)?;
                 if let Some(info_key_value) = opt {
                     info!("{}", "Succeessful to get last cache.".green().bold());
                     // If use_last_cache is set to true, there is db, the cache
@@ -99,7 +132,7 @@ impl Config {
             .with_context(|| format!("解析 `{:?}` 仓库的检查命令出错", self.uri))
     }
 
-    pub fn targets_specified(&self) -> TargetsSpecifed {
+    pub fn targets_specified(&self) -> TargetsSpecifed<'_> {
         self.config.targets_specified()
     }
 
@@ -114,7 +147,7 @@ impl Config {
             .into_iter()
             .map(|(pkg, info)| ListTargets {
                 user: self.user_name().into(),
-                repo: self.repo_name().into(),
+                repo: self.output_repo_name(),
                 pkg: pkg.into(),
                 targets: info.targets(),
             })
@@ -157,8 +190,10 @@ impl TryFrom<Value> for Config {
                 if let Ok(mut config) = RepoConfig::deserialize(deserializer) {
                     config.validate_checker_name(&repo)?;
                     config.sort_packages();
+                    let mut uri = uri::uri(repo)?;
+                    uri.set_rev(config.rev());
                     return Ok(Config {
-                        uri: uri::uri(repo)?,
+                        uri,
                         config: Box::new(config),
                         db: None,
                     });
@@ -282,9 +317,12 @@ impl TryFrom<Value> for Configs {
                         RepoConfig::deserialize(deserializer).with_context(|| PARSE_JSON_ERROR)?;
                     config.validate_checker_name(&repo)?;
                     config.validate_meta(&repo)?;
+                    config.validate_flags(&repo)?;
                     debug!(?config);
+                    let mut uri = uri::uri(repo)?;
+                    uri.set_rev(config.rev());
                     Ok(Config {
-                        uri: uri::uri(repo)?,
+                        uri,
                         config: Box::new(config),
                         db: None,
                     })
@@ -31,6 +31,8 @@ pub struct Resolve {
     pub target_overridden: bool,
     pub features_args: Vec<String>,
     pub env: IndexMap<String, String>,
+    /// 附加的 rustc flags 和 lint 参数：目前只有 clippy 使用
+    pub flags: Vec<XString>,
     pub toolchain: Option<usize>,
     pub checker: CheckerTool,
     /// 完整的检查命令字符串（一定包含 --target）：
@@ -53,6 +55,7 @@ impl Resolve {
             features_args: pkg.features_args.clone(),
             toolchain: pkg.toolchain,
             env: pkg.env.clone(),
+            flags: vec![],
             checker,
             cmd,
             expr,
@@ -76,6 +79,7 @@ impl Resolve {
             features_args: pkg.features_args.clone(),
             toolchain: pkg.toolchain,
             env: pkg.env.clone(),
+            flags: vec![],
             checker,
             cmd,
             expr,
@@ -93,6 +97,7 @@ impl Resolve {
             features_args: vec![],
             toolchain: self.toolchain,
             env: IndexMap::default(),
+            flags: vec![],
             checker: CheckerTool::Cargo,
             cmd: format!("VRITUAL={} cargo", self.checker.name()),
             expr: duct::cmd!("false"), // 无实际含义
@@ -110,6 +115,7 @@ impl Resolve {
             features_args: vec![],
             toolchain: None,
             env: IndexMap::default(),
+            flags: vec![],
             checker: CheckerTool::Cargo,
             cmd: "VRITUAL=LayoutParseError cargo".to_owned(),
             expr: duct::cmd!("false"), // 无实际含义
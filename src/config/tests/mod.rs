@@ -149,6 +149,37 @@ fn uri() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn uri_rev() -> Result<()> {
+    // 同一仓库的不同版本通过 `#label` 后缀区分
+    let json = r#"
+{
+  "os-checker/os-checker": { },
+  "os-checker/os-checker#stable": { "meta": { "rev": "v0.8.0" } },
+  "os-checker/os-checker#release": { "meta": { "rev": "release/0.8" } }
+}"#;
+    let configs = serde_json::from_str::<Configs>(json)?;
+    let join = configs
+        .0
+        .iter()
+        .map(|c| {
+            let (key, uri, rev, name) = (c.uri.key(), &c.uri, c.rev(), c.output_repo_name());
+            format!("{key} {uri:?} rev={rev:?} output={name}")
+        })
+        .join("\n");
+    let expected = expect![[r#"
+        os-checker/os-checker Github("os-checker/os-checker") rev=None output=os-checker
+        os-checker/os-checker#release Github("os-checker/os-checker") rev=Some("release/0.8") output=os-checker@release_0.8
+        os-checker/os-checker#stable Github("os-checker/os-checker") rev=Some("v0.8.0") output=os-checker@v0.8.0"#]];
+    expected.assert_eq(&join);
+
+    let err = Configs::from_json(r#"{"file://repos/arceos": { "meta": { "rev": "main" } }}"#)
+        .unwrap_err();
+    expect![[r#"meta.rev is not supported for the local repo "file://repos/arceos"; check out the revision in the local directory instead"#]].assert_eq(&err.to_string());
+
+    Ok(())
+}
+
 #[test]
 fn merge_configs() -> Result<()> {
     let a = r#"{"user1/repo": {}, "user2/repo": { "setup": "make setup" }}"#;
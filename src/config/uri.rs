@@ -1,17 +1,23 @@
-use crate::{cli::repos_base_dir, utils::git_clone, Result, XString};
+use crate::{
+    cli::repos_base_dir,
+    utils::{git_clone, CloneOptions},
+    Result, XString,
+};
 use cargo_metadata::camino::Utf8PathBuf;
 use eyre::{Context, ContextCompat};
+use os_checker_types::out_json::repo_name_with_rev;
 use regex::Regex;
 use serde::Serialize;
 use std::sync::LazyLock;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum UriTag {
     Github(String),
     Url(String),
     Local(Utf8PathBuf),
 }
 
+#[derive(Clone)]
 pub struct Uri {
     /// 代码库的来源
     tag: UriTag,
@@ -21,6 +27,10 @@ pub struct Uri {
     repo: XString,
     /// JSON config 中表示代码库来源的键
     key: String,
+    /// 检出的分支、标签或者提交 sha（来自 meta.rev）
+    rev: Option<String>,
+    /// 临时检出的提交，不影响本地目录（用于 history 子命令）
+    checkout: Option<String>,
 }
 
 impl Serialize for Uri {
@@ -62,30 +72,36 @@ impl std::hash::Hash for Uri {
 }
 
 impl Uri {
-    /// 获取该代码库的本地路径：如果指定 Github 或者 Url，则调用 git 命令下载
-    pub fn local_root_path_with_git_clone(&mut self) -> Result<Utf8PathBuf> {
+    /// 获取该代码库的本地路径：如果指定 Github 或者 Url，则调用 git 命令下载，并检出 rev
+    pub fn local_root_path_with_git_clone(&mut self, opts: &CloneOptions) -> Result<Utf8PathBuf> {
         let url = match &self.tag {
-            UriTag::Github(user_repo) => format!("https://github.com/{user_repo}.git"),
-            UriTag::Url(url) => url.clone(),
             UriTag::Local(p) => return Ok(p.clone()),
+            _ => self.url().unwrap(),
         };
 
         let repo_dir = self.repo_dir();
 
-        debug!(self.key, "git clone {url} {repo_dir}");
-        let (_, time_elapsed_ms) = git_clone(&repo_dir, &url)?;
+        debug!(self.key, ?opts, "git clone {url} {repo_dir}");
+        let time_elapsed_ms = git_clone(&repo_dir, &url, opts, self.checkout_rev())?;
         debug!(self.key, time_elapsed_ms);
 
         Ok(repo_dir)
     }
 
-    fn repo_dir(&self) -> Utf8PathBuf {
+    /// 待检出的提交：history 子命令设置的提交，或者 meta.rev。
+    fn checkout_rev(&self) -> Option<&str> {
+        self.checkout.as_deref().or(self.rev.as_deref())
+    }
+
+    pub fn repo_dir(&self) -> Utf8PathBuf {
         if let Some(path) = self.local_source() {
             return Utf8PathBuf::from(path).canonicalize_utf8().unwrap();
         }
         let mut dir = repos_base_dir();
         // 为了防止 repo 名在本地造成冲突，目录加上 user
-        dir.extend([&*self.user, &*self.repo]);
+        dir.push(&*self.user);
+        // 同一仓库的不同版本可以并存
+        dir.push(&*repo_name_with_rev(&self.repo, self.rev.as_deref()));
         dir
     }
 
@@ -114,6 +130,27 @@ impl Uri {
         &self.key
     }
 
+    pub fn set_rev(&mut self, rev: Option<&str>) {
+        self.rev = rev.map(String::from);
+    }
+
+    pub fn set_checkout(&mut self, checkout: &str) {
+        self.checkout = Some(checkout.to_owned());
+    }
+
+    /// The git url to clone from; None if Uri::Local.
+    pub fn url(&self) -> Option<String> {
+        match &self.tag {
+            UriTag::Github(user_repo) => Some(format!("https://github.com/{user_repo}.git")),
+            UriTag::Url(url) => Some(url.clone()),
+            UriTag::Local(_) => None,
+        }
+    }
+
+    pub fn is_github(&self) -> bool {
+        matches!(self.tag, UriTag::Github(_))
+    }
+
     /// Only returns Some if Uri::Local
     pub fn local_source(&self) -> Option<&str> {
         if let UriTag::Local(path) = &self.tag {
@@ -126,8 +163,10 @@ impl Uri {
 static USER_REPO: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"(.*/)*(?P<user>.*?)/(?P<repo>.*?)(\.git)?$"#).unwrap());
 
+/// `key` 可以带有 `#label` 后缀，它不影响代码库来源，仅用于区分同一仓库的多个配置（比如不同的 meta.rev）。
 pub fn uri(key: String) -> Result<Uri> {
-    let ((user, repo), tag) = match key.strip_prefix("file://") {
+    let source = key.split_once('#').map_or(&*key, |(source, _)| source);
+    let ((user, repo), tag) = match source.strip_prefix("file://") {
         Some(path) => {
             let path = Utf8PathBuf::from(path);
             (
@@ -136,15 +175,15 @@ pub fn uri(key: String) -> Result<Uri> {
             )
         }
         None => {
-            let tag = match key.matches('/').count() {
+            let tag = match source.matches('/').count() {
                 0 => bail!(
                     "{key} 不是正确的代码库来源；请指定以下一种格式：\
                  `file://localpath`；github 的 `user/repo`；完整的 git 仓库地址"
                 ),
-                1 => UriTag::Github(key.as_str().into()),
-                _ => UriTag::Url(key.as_str().into()),
+                1 => UriTag::Github(source.into()),
+                _ => UriTag::Url(source.into()),
             };
-            (user_repo(&key)?, tag)
+            (user_repo(source)?, tag)
         }
     };
     Ok(Uri {
@@ -152,6 +191,8 @@ pub fn uri(key: String) -> Result<Uri> {
         user,
         repo,
         key,
+        rev: None,
+        checkout: None,
     })
 }
 
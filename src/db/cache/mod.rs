@@ -1,12 +1,14 @@
 use crate::{
     config::{CheckerTool, Resolve},
+    layout::expected_advisory_db_commit,
     output::{get_channel, Cmd, Data, Kind},
+    utils::{cached_checker_version, checker_version},
     Result, XString,
 };
 use camino::{Utf8Path, Utf8PathBuf};
 use duct::cmd;
 use indexmap::IndexMap;
-use os_checker_types::db as out;
+use os_checker_types::{db as out, DataDetail};
 use std::fmt;
 
 mod type_conversion;
@@ -26,8 +28,14 @@ impl CacheRepoKeyCmd {
             pkg_name: resolve.pkg_name.clone(),
             checker: CacheChecker {
                 checker: resolve.checker,
-                version: None,
-                sha: None,
+                // a checker upgrade invalidates its cached results
+                version: checker_version(resolve),
+                // the advisory-db commit invalidates audit results when the db changes
+                sha: resolve
+                    .audit
+                    .as_deref()
+                    .and_then(|a| a.db_commit())
+                    .map(String::from),
             },
             cmd: CacheCmd {
                 cmd: resolve.cmd.clone(),
@@ -35,11 +43,32 @@ impl CacheRepoKeyCmd {
                 channel: get_channel(resolve.toolchain.unwrap_or(0)),
                 env: resolve.env.clone(),
                 features: resolve.features_args.iter().map(|s| s.into()).collect(),
-                // TODO: 待支持
-                flags: vec![],
+                flags: resolve.flags.clone(),
             },
         }
     }
+
+    /// 检查工具在缓存之后是否升级，或者 audit 所用的 advisory-db 是否更新：此时缓存的结果不再可用。
+    fn is_outdated(&self) -> bool {
+        let CacheChecker {
+            checker,
+            version,
+            sha,
+        } = &self.checker;
+        let current = cached_checker_version(*checker, &self.cmd.channel);
+        if *version != current {
+            warn!(?checker, cached = ?version, ?current, "checker version changed");
+            return true;
+        }
+        if *checker == CheckerTool::Audit {
+            let current = expected_advisory_db_commit();
+            if *sha != current {
+                warn!(cached = ?sha, ?current, "advisory-db commit changed");
+                return true;
+            }
+        }
+        false
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -71,6 +100,14 @@ impl CacheRepoKey {
         &self.cmd.pkg_name
     }
 
+    pub fn checker(&self) -> CheckerTool {
+        self.cmd.checker.checker
+    }
+
+    pub fn is_outdated(&self) -> bool {
+        self.cmd.is_outdated()
+    }
+
     pub fn to_db_key(&self) -> out::CacheRepoKey {
         self.clone().into()
     }
@@ -85,14 +122,22 @@ pub struct CacheRepo {
 }
 
 impl CacheRepo {
-    pub fn new(user: &str, repo: &str, root: &Utf8Path) -> Result<Self> {
+    /// rev 为 meta.rev：检出标签或者提交时处于 detached HEAD，因此用它代替当前分支名。
+    pub fn new(user: &str, repo: &str, root: &Utf8Path, rev: Option<&str>) -> Result<Self> {
         let sha = cmd!("git", "rev-parse", "HEAD").dir(root).read()?;
-        let branch = cmd!("git", "branch", "--show-current").dir(root).read()?;
+        let branch = match rev {
+            Some(rev) => rev.into(),
+            None => cmd!("git", "branch", "--show-current")
+                .dir(root)
+                .read()?
+                .trim()
+                .into(),
+        };
         Ok(Self {
             user: user.into(),
             repo: repo.into(),
             sha: sha.trim().to_owned(),
-            branch: branch.trim().into(),
+            branch,
         })
     }
 
@@ -127,7 +172,7 @@ struct CacheCmd {
     env: IndexMap<String, String>,
     // Below is not necessary, and currently not implemented.
     features: Vec<XString>,
-    /// rustcflags
+    /// rustc flags and lint arguments appended to the checker
     flags: Vec<XString>,
 }
 
@@ -151,11 +196,24 @@ pub struct OutputDataInner {
     file: Utf8PathBuf,
     kind: Kind,
     raw: String,
+    fingerprint: String,
+    detail: DataDetail,
 }
 
 impl OutputDataInner {
-    pub fn new(file: Utf8PathBuf, kind: Kind, raw: String) -> Self {
-        Self { file, kind, raw }
+    pub fn new(file: Utf8PathBuf, kind: Kind, raw: String, fingerprint: String) -> Self {
+        Self {
+            file,
+            kind,
+            raw,
+            fingerprint,
+            detail: DataDetail::default(),
+        }
+    }
+
+    pub fn with_detail(mut self, detail: DataDetail) -> Self {
+        self.detail = detail;
+        self
     }
 }
 
@@ -193,6 +251,8 @@ impl CacheValue {
             file: d.file.clone(),
             kind: d.kind,
             raw: d.raw.clone(),
+            fingerprint: d.fingerprint.clone(),
+            detail: d.detail.clone(),
         }));
     }
 
@@ -2,7 +2,11 @@ use super::{
     CacheChecker, CacheCmd, CacheRepo, CacheRepoKey, CacheRepoKeyCmd, CacheValue, OutputData,
     OutputDataInner,
 };
-use crate::{config::CheckerTool, output::Kind};
+use crate::{
+    config::CheckerTool,
+    output::{Fingerprint, Kind},
+};
+use cargo_metadata::camino::Utf8Path;
 use os_checker_types::cache as out;
 
 // ********** CLI => os_checker_types **********
@@ -95,9 +99,21 @@ impl From<OutputData> for out::OutputData {
 
 impl From<OutputDataInner> for out::OutputDataInner {
     fn from(value: OutputDataInner) -> Self {
-        let OutputDataInner { file, kind, raw } = value;
+        let OutputDataInner {
+            file,
+            kind,
+            raw,
+            fingerprint,
+            detail,
+        } = value;
         let kind = kind.into();
-        Self { file, kind, raw }
+        Self {
+            file,
+            kind,
+            raw,
+            fingerprint,
+            detail,
+        }
     }
 }
 
@@ -250,9 +266,21 @@ impl From<out::OutputData> for OutputData {
 
 impl From<out::OutputDataInner> for OutputDataInner {
     fn from(value: out::OutputDataInner) -> Self {
-        let out::OutputDataInner { file, kind, raw } = value;
+        let out::OutputDataInner {
+            file,
+            kind,
+            raw,
+            fingerprint,
+            detail,
+        } = value;
         let kind = kind.into();
-        Self { file, kind, raw }
+        Self {
+            file,
+            kind,
+            raw,
+            fingerprint,
+            detail,
+        }
     }
 }
 
@@ -263,10 +291,26 @@ impl From<out::CacheValue> for CacheValue {
             cmd,
             diagnostics,
         } = value;
-        Self {
+        let mut value = Self {
             unix_timestamp_milli,
             cmd: cmd.into(),
             diagnostics: diagnostics.into(),
+        };
+        value.fill_legacy_fingerprints();
+        value
+    }
+}
+
+impl CacheValue {
+    /// 旧缓存中没有 fingerprint：此时无法得知 lint code 和所在 item，只能退而求其次，
+    /// 从 checker、kind、文件和归一化的原始输出计算。
+    fn fill_legacy_fingerprints(&mut self) {
+        let checker = self.cmd.checker.checker;
+        for d in &mut self.diagnostics.data {
+            if d.fingerprint.is_empty() {
+                d.fingerprint =
+                    Fingerprint::new(checker, d.kind, &d.raw, &d.file).hash(Utf8Path::new(""));
+            }
         }
     }
 }
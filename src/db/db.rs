@@ -6,7 +6,7 @@ use os_checker_types::db::{
     CacheLayout, CacheRepoKey, CacheValue, CheckValue, Info, InfoKey, CHECKS, DATA, INFO, LAYOUT,
 };
 use redb::{Database, Key, ReadableTable, ReadableTableMetadata, Table, TableDefinition, Value};
-use std::{cell::RefCell, sync::Arc};
+use std::{cell::RefCell, collections::HashSet, sync::Arc};
 
 #[derive(Clone)]
 pub struct Db {
@@ -36,6 +36,10 @@ impl Db {
         Ok(db)
     }
 
+    pub(super) fn database(&self) -> &Database {
+        &self.db
+    }
+
     pub fn get_info(&self, key: &InfoKey) -> Result<Option<Info>> {
         self.read(INFO, key)
     }
@@ -210,3 +214,193 @@ impl Db {
         Ok(())
     }
 }
+
+/// 检查记录的保留策略：满足任一条件的检查记录被保留。
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    /// Keep the last N check items.
+    pub keep_checks: Option<usize>,
+    /// Keep check items started within N days.
+    pub keep_days: Option<u64>,
+}
+
+/// The number of removed items in each table.
+#[derive(Debug, Default)]
+pub struct GcStats {
+    pub checks: usize,
+    pub info: usize,
+    pub layout: usize,
+    pub data: usize,
+}
+
+impl Db {
+    /// 删除保留策略之外的检查记录，以及不再被保留的检查记录引用的 Info、CacheLayout 和 CacheValue。
+    ///
+    /// 引用关系为 CheckValue => InfoKey => Info.caches => CacheRepoKey；
+    /// 键的比较方式与 redb 一致，即比较编码后的字节。
+    pub fn gc(&self, retention: Retention) -> Result<GcStats> {
+        let mut stats = GcStats::default();
+        let txn = self.db.begin_write()?;
+
+        let mut info_keys = HashSet::new();
+        {
+            let mut checks = txn.open_table(CHECKS)?;
+            let len = checks.len()? as usize;
+            ensure!(
+                len != 0,
+                "No check item is in {CHECKS}, so nothing would be kept; refuse to gc."
+            );
+            let earliest = retention
+                .keep_days
+                .map(|days| os_checker_types::now().saturating_sub(days * 24 * 3600 * 1000));
+            let mut idx = 0;
+            checks.retain(|_, check| {
+                let nth_last = len - idx;
+                idx += 1;
+                // always keep the last check item which may be in progress
+                let keep = nth_last == 1
+                    || retention.keep_checks.is_some_and(|n| nth_last <= n)
+                    || earliest.is_some_and(|t| check.timestamp_start >= t);
+                if keep {
+                    info_keys.extend(check.keys.iter().map(|k| InfoKey::as_bytes(&k.info)));
+                } else {
+                    stats.checks += 1;
+                }
+                keep
+            })?;
+        }
+
+        let mut data_keys = HashSet::new();
+        {
+            let mut info = txn.open_table(INFO)?;
+            info.retain(|key, info| {
+                let keep = info_keys.contains(&InfoKey::as_bytes(&key));
+                if keep {
+                    data_keys.extend(info.caches.iter().map(CacheRepoKey::as_bytes));
+                } else {
+                    stats.info += 1;
+                }
+                keep
+            })?;
+        }
+        {
+            let mut layout = txn.open_table(LAYOUT)?;
+            layout.retain(|key, _| {
+                let keep = info_keys.contains(&InfoKey::as_bytes(&key));
+                stats.layout += usize::from(!keep);
+                keep
+            })?;
+        }
+        {
+            let mut data = txn.open_table(DATA)?;
+            data.retain(|key, _| {
+                let keep = data_keys.contains(&CacheRepoKey::as_bytes(&key));
+                stats.data += usize::from(!keep);
+                keep
+            })?;
+        }
+
+        txn.commit()?;
+        info!(
+            ?retention,
+            ?stats,
+            "Successfully removed unreferenced cache."
+        );
+        Ok(stats)
+    }
+}
+
+#[test]
+fn gc_unreferenced_cache() -> Result<()> {
+    use os_checker_types::{
+        cache::{CacheChecker, CacheCmd, CacheRepo, CacheRepoKeyCmd, OutputData},
+        config::RepoConfig,
+        info::{Committer, LatestCommit},
+        CheckerTool,
+    };
+
+    let path = std::env::temp_dir().join(format!("os-checker-gc-{}.redb", std::process::id()));
+    let path = Utf8Path::from_path(&path).unwrap();
+    let db = Db::new(path)?;
+
+    let repo = |name: &str| CacheRepo {
+        user: "user".into(),
+        repo: name.into(),
+        sha: "sha".into(),
+        branch: "main".into(),
+    };
+    let cache_key = |name: &str| CacheRepoKey {
+        repo: repo(name),
+        cmd: CacheRepoKeyCmd {
+            pkg_name: name.into(),
+            checker: CacheChecker {
+                checker: CheckerTool::Clippy,
+                version: None,
+                sha: None,
+            },
+            cmd: CacheCmd {
+                cmd: "cargo clippy".into(),
+                target: "x86_64-unknown-linux-gnu".into(),
+                channel: "nightly".into(),
+                env: Default::default(),
+                features: vec![],
+                flags: vec![],
+            },
+        },
+    };
+    let info_key = |name: &str| InfoKey {
+        repo: repo(name),
+        config: RepoConfig::default(),
+    };
+    let committer = || Committer {
+        datetime: 0,
+        email: String::new(),
+        name: "".into(),
+    };
+
+    // each check item checks a different repo, plus an orphan cache
+    for name in ["a", "b", "c", "orphan"] {
+        let value = CacheValue {
+            unix_timestamp_milli: 0,
+            cmd: cache_key(name).cmd,
+            diagnostics: OutputData {
+                duration_ms: 0,
+                data: vec![],
+            },
+        };
+        db.set_cache(&cache_key(name), &value)?;
+        if name == "orphan" {
+            continue;
+        }
+        let info = Info {
+            complete: true,
+            caches: vec![cache_key(name)],
+            latest_commit: LatestCommit {
+                sha: String::new(),
+                mes: String::new(),
+                author: committer(),
+                committer: committer(),
+            },
+        };
+        db.set_info(&info_key(name), &info)?;
+        db.new_check()?;
+        db.check_push_info_key(info_key(name))?;
+        db.check_set_complete()?;
+    }
+
+    let stats = db.gc(Retention {
+        keep_checks: Some(2),
+        keep_days: None,
+    })?;
+    let removed = [stats.checks, stats.info, stats.layout, stats.data];
+    assert_eq!(removed, [1, 1, 0, 2]);
+    assert!(db.get_info(&info_key("a"))?.is_none());
+    assert!(db.get_info(&info_key("b"))?.is_some());
+    assert!(db.get_cache(&cache_key("a"))?.is_none());
+    assert!(db.get_cache(&cache_key("orphan"))?.is_none());
+    assert!(db.get_cache(&cache_key("c"))?.is_some());
+
+    drop(db);
+    std::fs::remove_file(path)?;
+    Ok(())
+}
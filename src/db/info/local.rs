@@ -8,16 +8,35 @@ use time::format_description::well_known::Rfc2822;
 
 pub fn info_repo(path: &str) -> Result<(String, LatestCommit)> {
     let branch = current_branch(path)?;
-    let commit = latest_commit(path)?;
+    let commit = latest_commit(path, "HEAD")?;
     Ok((branch, commit))
 }
 
+/// 离线时从已存在的仓库目录获取：rev 与检出时一样，优先解析为远程分支 `origin/{rev}`。
+pub fn info_repo_rev(path: &str, rev: Option<&str>) -> Result<(String, LatestCommit)> {
+    let Some(rev) = rev else {
+        return info_repo(path);
+    };
+    let remote = format!("origin/{rev}");
+    let is_remote_branch = cmd!("git", "rev-parse", "--verify", "--quiet", &remote)
+        .dir(path)
+        .stdout_null()
+        .unchecked()
+        .run()?
+        .status
+        .success();
+    let commit = latest_commit(path, if is_remote_branch { &remote } else { rev })
+        .with_context(|| format!("无法在 {path} 中找到分支、标签或者提交 `{rev}`"))?;
+    Ok((rev.to_owned(), commit))
+}
+
 fn current_branch(path: &str) -> Result<String> {
     let branch = cmd!("git", "branch", "--show-current").dir(path).read()?;
     Ok(branch.trim().to_owned())
 }
 
-fn latest_commit(path: &str) -> Result<LatestCommit> {
+/// rev 为 HEAD、FETCH_HEAD 等任何可解析为提交的引用。
+pub(super) fn latest_commit(path: &str, rev: &str) -> Result<LatestCommit> {
     // git log -1 --pretty=tformat:"SHA: %H%nAuthor: %an <%ae>%nCommitter: %cn <%ce>%nCommit Header: %s"
     let output = cmd!(
         "git",
@@ -25,7 +44,8 @@ fn latest_commit(path: &str) -> Result<LatestCommit> {
         "-1",
         "--pretty=tformat:SHA: %H%nCommit Header: %s%nAuthor: %an%nAuthor Email: %ae%n\
             Author Date: %ad%nCommitter: %cn%nCommitter Email: %ce%nCommitter Date: %cd",
-        "--date=rfc"
+        "--date=rfc",
+        rev
     )
     .dir(path)
     .read()?;
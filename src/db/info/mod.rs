@@ -1,16 +1,25 @@
 use super::{out::CacheLayout, CacheRepo, CacheRepoKey, CacheValue, Db};
 use crate::{
+    cli::offline,
     config::{RepoConfig, Uri},
+    utils::{
+        env_var::{remote_info, RemoteInfo},
+        is_local_url, skipped_checkers,
+    },
     Result, XString,
 };
 use duct::cmd;
 use eyre::Context;
-use os_checker_types::{db as out, out_json::UserRepo};
+use os_checker_types::{
+    db as out,
+    out_json::{repo_name_with_rev, UserRepo},
+};
 use serde::Deserialize;
-use std::{cell::RefCell, fmt};
+use std::{cell::RefCell, fmt, sync::LazyLock};
 
 mod local;
 pub mod read_cache;
+mod remote;
 mod type_conversion;
 
 /// Needs CLIs like gh and jq.
@@ -42,10 +51,11 @@ impl InfoKey {
         self.clone().into()
     }
 
+    /// 与输出中的仓库名一致：同一仓库的不同 meta.rev 各自使用最近的缓存。
     fn user_repo(&self) -> UserRepo {
         UserRepo {
             user: self.repo.user.clone(),
-            repo: self.repo.repo.clone(),
+            repo: repo_name_with_rev(&self.repo.repo, self.config.rev()),
         }
     }
 }
@@ -65,6 +75,18 @@ impl Info {
         self.complete
     }
 
+    /// 所有检查结果的键都未过时，才能直接使用整个仓库的缓存。
+    ///
+    /// 这会检测缓存中每个检查工具的版本（因此也记录了 `env.tools.checkers`）和 advisory-db 的提交。
+    pub fn is_up_to_date(&self) -> bool {
+        // 离线时被跳过的检查不会输出缓存的结果，因此无需检查它们
+        let skipped = skipped_checkers();
+        self.caches
+            .iter()
+            .filter(|key| !skipped.iter().any(|(c, _)| *c == key.checker()))
+            .all(|key| !key.is_outdated())
+    }
+
     pub fn get_cache_values(&self, db: &Db) -> Result<Vec<(&str, CacheValue)>> {
         let caches_len = self.caches.len();
         let mut v = Vec::with_capacity(caches_len);
@@ -133,29 +155,85 @@ fn parse_datetime(dt: &str) -> u64 {
     super::unix_timestamp_milli(local)
 }
 
-fn info_repo(user: &str, repo: &str) -> Result<(String, LatestCommit)> {
-    let branch = default_branch(user, repo)?;
-    let arg = format!("repos/{user}/{repo}/branches/{branch}");
-    let jq = "{
+fn info_repo(user: &str, repo: &str, rev: Option<&str>) -> Result<(String, LatestCommit)> {
+    let (branch, arg, jq) = match rev {
+        // 分支、标签或者提交 sha 均可通过 commits API 查询
+        Some(rev) => (
+            rev.to_owned(),
+            format!("repos/{user}/{repo}/commits/{rev}"),
+            "{
+              sha: .sha,
+              mes: .commit.message,
+              author: .commit.author,
+              committer: .commit.committer
+          }",
+        ),
+        None => {
+            let branch = default_branch(user, repo)?;
+            let arg = format!("repos/{user}/{repo}/branches/{branch}");
+            let jq = "{
               sha: .commit.sha,
               mes: .commit.commit.message,
               author: .commit.commit.author,
               committer: .commit.commit.committer
           }";
+            (branch, arg, jq)
+        }
+    };
     let last_commit = serde_json::from_str(&gh_api(arg, jq.to_owned())?)
         .with_context(|| "无法获取仓库最新提交信息")?;
     Ok((branch, last_commit))
 }
 
-/// Query latest commit sha via `gh api`, and return the key and value with empty caches.
+/// 只有 Github 仓库能使用 Github API；其他仓库总是使用 git。
+fn use_github_api(uri: &Uri) -> bool {
+    static GH: LazyLock<bool> = LazyLock::new(|| {
+        let available = cmd!("gh", "--version")
+            .stdout_null()
+            .stderr_null()
+            .run()
+            .is_ok();
+        if !available {
+            warn!("gh is not available; fall back to git for Github repos");
+        }
+        available
+    });
+    uri.is_github()
+        && match remote_info() {
+            RemoteInfo::Auto => *GH,
+            RemoteInfo::Github => true,
+            RemoteInfo::Git => false,
+        }
+}
+
+/// Query latest commit sha via `gh api` or git, and return the key and value with empty caches.
 pub fn get_info(uri: &Uri, config: RepoConfig) -> Result<InfoKeyValue> {
     let [user, repo] = [uri.user_name(), uri.repo_name()];
+    let rev = config.rev();
     let (branch, latest_commit) = if let Some(path) = uri.local_source() {
         info!(?uri, "Get info from local project");
         local::info_repo(path)?
+    } else if offline() {
+        let dir = uri.repo_dir();
+        let url = uri.url().unwrap();
+        if dir.exists() {
+            info!(?uri, rev, %dir, "Get info from the existing repo dir (offline)");
+            local::info_repo_rev(dir.as_str(), rev)?
+        } else {
+            ensure!(
+                is_local_url(&url),
+                "offline: {dir} doesn't exist and {url} isn't a local repo; \
+                 clone it before running with --offline"
+            );
+            info!(?uri, rev, "Get info from the local git repo (offline)");
+            remote::info_repo(&url, rev)?
+        }
+    } else if use_github_api(uri) {
+        info!(?uri, rev, "Get info from Github API");
+        info_repo(user, repo, rev)?
     } else {
-        info!(?uri, "Get info from Github API");
-        info_repo(user, repo)?
+        info!(?uri, rev, "Get info from git remote");
+        remote::info_repo(&uri.url().unwrap(), rev)?
     };
     let key = InfoKey {
         repo: CacheRepo::new_with_sha(user, repo, &latest_commit.sha, branch),
@@ -222,6 +300,9 @@ impl InfoKeyValue {
     }
 
     pub fn check_push_info_key(&self, db: &Db) -> Result<()> {
+        if !crate::cli::push_check_item() {
+            return Ok(());
+        }
         db.check_push_info_key(self.key.clone().into())
     }
 }
@@ -236,6 +317,6 @@ fn github_date() {
 fn get_default_branch() -> Result<()> {
     let user = "os-checker";
     let repo = "os-checker";
-    dbg!(default_branch(user, repo)?, info_repo(user, repo)?);
+    dbg!(default_branch(user, repo)?, info_repo(user, repo, None)?);
     Ok(())
 }
@@ -59,10 +59,14 @@ pub struct InfoKeyValuePair {
 }
 
 impl InfoKeyValuePair {
+    /// 用于重新检查该仓库：检查结果的键会重新添加，因此清空已缓存的键。
     pub fn to_info_key_value(&self) -> InfoKeyValue {
+        let mut val = self.val.clone();
+        val.complete = false;
+        val.caches.clear();
         InfoKeyValue {
             key: self.key.clone(),
-            val: RefCell::new(self.val.clone()),
+            val: RefCell::new(val),
         }
     }
 }
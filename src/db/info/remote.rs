@@ -0,0 +1,106 @@
+//! Get info for any git remote via plain git commands, without the Github CLI.
+use super::{local::latest_commit, LatestCommit};
+use crate::Result;
+use duct::cmd;
+use eyre::{Context, ContextCompat};
+use os_checker_types::unix_timestamp_milli;
+
+/// 返回检查的分支名（或者 rev）和该提交的信息。
+///
+/// 默认分支通过 `git ls-remote --symref` 获取；提交信息则通过浅拉取（不含文件树）到临时的
+/// 裸仓库后，由 `git log` 获取，因此无需克隆整个仓库。
+pub fn info_repo(url: &str, rev: Option<&str>) -> Result<(String, LatestCommit)> {
+    let branch = match rev {
+        Some(rev) => rev.to_owned(),
+        None => default_branch(url)?,
+    };
+
+    let dir = std::env::temp_dir().join(format!(
+        "os-checker-remote-info-{}-{}",
+        std::process::id(),
+        unix_timestamp_milli(time::OffsetDateTime::now_utc())
+    ));
+    let dir = camino::Utf8PathBuf::from_path_buf(dir).unwrap();
+    let commit = (|| {
+        cmd!("git", "init", "-q", "--bare", &dir).read()?;
+        cmd!(
+            "git",
+            "fetch",
+            "-q",
+            "--depth=1",
+            "--filter=tree:0",
+            url,
+            rev.unwrap_or("HEAD")
+        )
+        .dir(&dir)
+        .stderr_capture()
+        .read()
+        .with_context(|| format!("无法从 {url} 拉取 {branch}"))?;
+        latest_commit(dir.as_str(), "FETCH_HEAD")
+    })();
+    if let Err(err) = std::fs::remove_dir_all(&dir) {
+        warn!(%dir, ?err, "无法删除临时目录");
+    }
+
+    Ok((branch, commit?))
+}
+
+fn default_branch(url: &str) -> Result<String> {
+    let output = cmd!("git", "ls-remote", "--symref", url, "HEAD")
+        .read()
+        .with_context(|| format!("无法获取 {url} 的默认分支"))?;
+    parse_symref(&output).with_context(|| format!("{url} 的 HEAD 不指向分支：{output:?}"))
+}
+
+/// `git ls-remote --symref url HEAD` outputs tab-separated lines like
+/// `ref: refs/heads/main<TAB>HEAD` and `<sha><TAB>HEAD`.
+fn parse_symref(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (symref, head) = line.strip_prefix("ref: ")?.split_once('\t')?;
+        (head == "HEAD").then_some(symref.strip_prefix("refs/heads/")?.to_owned())
+    })
+}
+
+#[test]
+fn symref() {
+    let output = "ref: refs/heads/dev\tHEAD\n4c603baeb747801449a7d20fff4a5be08624c4db\tHEAD";
+    assert_eq!(parse_symref(output).as_deref(), Some("dev"));
+    assert_eq!(
+        parse_symref("4c603baeb747801449a7d20fff4a5be08624c4db\tHEAD"),
+        None
+    );
+}
+
+#[test]
+fn local_bare_repo() -> Result<()> {
+    let base = std::env::temp_dir().join(format!("os-checker-bare-{}", std::process::id()));
+    let base = camino::Utf8PathBuf::from_path_buf(base).unwrap();
+    let [work, bare] = [base.join("work"), base.join("bare.git")];
+    std::fs::create_dir_all(&work)?;
+
+    let git = |args: &[&str]| cmd("git", args).dir(&work).stdout_null().run();
+    git(&["init", "-q", "-b", "trunk"])?;
+    let user = ["-c", "user.name=os-checker", "-c", "user.email=a@b"];
+    git(&[&user[..], &["commit", "-q", "--allow-empty", "-m", "init"]].concat())?;
+    git(&["tag", "v1"])?;
+    cmd!("git", "clone", "-q", "--bare", &work, &bare).run()?;
+
+    let (branch, commit) = info_repo(bare.as_str(), None)?;
+    assert_eq!(branch, "trunk");
+    assert_eq!(commit.mes, "init");
+    assert_eq!(commit.author.name, "os-checker");
+
+    let (tag, tagged) = info_repo(bare.as_str(), Some("v1"))?;
+    assert_eq!(tag, "v1");
+    assert_eq!(tagged.sha, commit.sha);
+
+    // 离线时从已克隆的仓库目录获取，分支解析为 origin/trunk
+    let clone = base.join("clone");
+    cmd!("git", "clone", "-q", &bare, &clone).run()?;
+    let (branch, local) = super::local::info_repo_rev(clone.as_str(), Some("trunk"))?;
+    assert_eq!((branch.as_str(), &*local.sha), ("trunk", &*commit.sha));
+    assert!(super::local::info_repo_rev(clone.as_str(), Some("nonexistent")).is_err());
+
+    std::fs::remove_dir_all(&base)?;
+    Ok(())
+}
@@ -3,7 +3,13 @@ pub use cache::*;
 
 #[allow(clippy::module_inception)]
 mod db;
-pub use db::Db;
+pub use db::{Db, Retention};
+
+/// export, import and merge
+mod transfer;
+
+mod query;
+pub use query::Query;
 
 /// Github APIs
 mod info;
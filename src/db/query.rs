@@ -0,0 +1,467 @@
+//! 查询缓存中的检查记录、仓库信息和检查结果概要，用于排查仓库的检查结果为何过时。
+
+use super::{parse_unix_timestamp_milli, Db};
+use crate::Result;
+use os_checker_types::{
+    db::{CacheRepo, CacheRepoKey, CacheValue, Info, InfoKey, CHECKS, DATA, INFO},
+    CheckerTool,
+};
+use redb::{ReadableTable, Value};
+use serde::Serialize;
+use std::{collections::HashSet, fmt::Write};
+
+/// 查询条件：未指定的条件不参与过滤。
+#[derive(Debug, Default)]
+pub struct Query {
+    pub user: Option<String>,
+    pub repo: Option<String>,
+    /// A prefix of the commit sha.
+    pub sha: Option<String>,
+    pub checker: Option<CheckerTool>,
+    pub pkg: Option<String>,
+    /// The index of a check item.
+    pub check: Option<u32>,
+}
+
+impl Query {
+    fn match_repo(&self, repo: &CacheRepo) -> bool {
+        self.user.as_deref().map_or(true, |user| repo.user == user)
+            && self.repo.as_deref().map_or(true, |name| repo.repo == name)
+            && self
+                .sha
+                .as_deref()
+                .map_or(true, |sha| repo.sha.starts_with(sha))
+    }
+
+    fn match_cache(&self, key: &CacheRepoKey) -> bool {
+        self.checker.map_or(true, |c| key.cmd.checker.checker == c)
+            && self
+                .pkg
+                .as_deref()
+                .map_or(true, |pkg| key.cmd.pkg_name == pkg)
+    }
+
+    fn filter_repos(&self) -> bool {
+        self.user.is_some() || self.repo.is_some() || self.sha.is_some()
+    }
+
+    fn filter_caches(&self) -> bool {
+        self.checker.is_some() || self.pkg.is_some()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryOutput {
+    pub checks: Vec<QueryCheck>,
+    pub repos: Vec<QueryRepo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryCheck {
+    pub idx: u32,
+    pub timestamp_start: u64,
+    /// 0 means checks are not finished.
+    pub timestamp_end: u64,
+    /// The number of all repos in the check item.
+    pub total: usize,
+    /// Matched repos like `user/repo@sha`.
+    pub repos: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryRepo {
+    pub user: String,
+    pub repo: String,
+    pub sha: String,
+    pub branch: String,
+    pub complete: bool,
+    pub latest_commit: QueryCommit,
+    pub caches: Vec<QueryCache>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryCommit {
+    pub sha: String,
+    pub mes: String,
+    pub author: String,
+    pub datetime: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryCache {
+    pub pkg: String,
+    pub checker: CheckerTool,
+    pub version: Option<String>,
+    pub target: String,
+    pub channel: String,
+    pub cmd: String,
+    /// None if the cache key points to nothing in DATA.
+    pub value: Option<QueryCacheValue>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryCacheValue {
+    pub timestamp: u64,
+    pub duration_ms: u64,
+    pub diagnostics: usize,
+}
+
+impl Db {
+    pub fn query(&self, query: &Query) -> Result<QueryOutput> {
+        let txn = self.database().begin_read()?;
+
+        let mut checks = Vec::new();
+        let mut info_keys = Vec::new();
+        let mut seen = HashSet::new();
+        for item in txn.open_table(CHECKS)?.iter()? {
+            let (idx, check) = item?;
+            let (idx, check) = (idx.value(), check.value());
+            if query.check.is_some_and(|i| i != idx) {
+                continue;
+            }
+            let matched: Vec<_> = (check.keys.iter())
+                .filter(|k| query.match_repo(&k.info.repo))
+                .collect();
+            if matched.is_empty() && query.filter_repos() {
+                continue;
+            }
+            if query.check.is_some() {
+                for key in &matched {
+                    if seen.insert(InfoKey::as_bytes(&key.info)) {
+                        info_keys.push(key.info.clone());
+                    }
+                }
+            }
+            checks.push(QueryCheck {
+                idx,
+                timestamp_start: check.timestamp_start,
+                timestamp_end: check.timestamp_end,
+                total: check.keys.len(),
+                repos: matched.iter().map(|k| repo_sha(&k.info.repo)).collect(),
+            });
+        }
+
+        let info_table = txn.open_table(INFO)?;
+        let mut infos = Vec::new();
+        if query.check.is_some() {
+            for key in info_keys {
+                match info_table.get(&key)? {
+                    Some(info) => infos.push((key, info.value())),
+                    None => warn!(repo = repo_sha(&key.repo), "No Info in {INFO}."),
+                }
+            }
+        } else {
+            for item in info_table.iter()? {
+                let (key, info) = item?;
+                let key = key.value();
+                if query.match_repo(&key.repo) {
+                    infos.push((key, info.value()));
+                }
+            }
+        }
+
+        let data = txn.open_table(DATA)?;
+        let mut repos = Vec::with_capacity(infos.len());
+        for (key, info) in infos {
+            let mut caches = Vec::new();
+            for cache_key in info.caches.iter().filter(|k| query.match_cache(k)) {
+                let value = data.get(cache_key)?.map(|guard| guard.value());
+                caches.push(QueryCache::new(cache_key, value.as_ref()));
+            }
+            if caches.is_empty() && query.filter_caches() {
+                continue;
+            }
+            repos.push(QueryRepo::new(key, info, caches));
+        }
+
+        Ok(QueryOutput { checks, repos })
+    }
+}
+
+fn repo_sha(repo: &CacheRepo) -> String {
+    let sha = &repo.sha[..repo.sha.len().min(8)];
+    format!("{}/{}@{sha}", repo.user, repo.repo)
+}
+
+impl QueryRepo {
+    fn new(key: InfoKey, info: Info, caches: Vec<QueryCache>) -> Self {
+        let CacheRepo {
+            user,
+            repo,
+            sha,
+            branch,
+        } = key.repo;
+        let commit = info.latest_commit;
+        QueryRepo {
+            user: user.into(),
+            repo: repo.into(),
+            sha,
+            branch: branch.into(),
+            complete: info.complete,
+            latest_commit: QueryCommit {
+                sha: commit.sha,
+                mes: commit.mes,
+                author: commit.author.name.into(),
+                datetime: commit.author.datetime,
+            },
+            caches,
+        }
+    }
+}
+
+impl QueryCache {
+    fn new(key: &CacheRepoKey, value: Option<&CacheValue>) -> Self {
+        let cmd = &key.cmd;
+        QueryCache {
+            pkg: cmd.pkg_name.to_string(),
+            checker: cmd.checker.checker,
+            version: cmd.checker.version.clone(),
+            target: cmd.cmd.target.clone(),
+            channel: cmd.cmd.channel.clone(),
+            cmd: cmd.cmd.cmd.clone(),
+            value: value.map(|v| QueryCacheValue {
+                timestamp: v.unix_timestamp_milli,
+                duration_ms: v.diagnostics.duration_ms,
+                diagnostics: v.diagnostics.data.len(),
+            }),
+        }
+    }
+}
+
+fn datetime(ts: u64) -> String {
+    if ts == 0 {
+        return "-".to_owned();
+    }
+    let t = parse_unix_timestamp_milli(ts);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+        t.year(),
+        t.month() as u8,
+        t.day(),
+        t.hour(),
+        t.minute(),
+        t.second()
+    )
+}
+
+/// 左对齐的纯文本表格。
+fn table(out: &mut String, title: &str, header: &[&str], rows: Vec<Vec<String>>) {
+    let mut width: Vec<_> = header.iter().map(|h| h.chars().count()).collect();
+    for row in &rows {
+        for (w, cell) in width.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    _ = writeln!(out, "{title} ({})", rows.len());
+    let mut line = |cells: &mut dyn Iterator<Item = &str>| {
+        let mut s = String::new();
+        for (cell, w) in cells.zip(&width) {
+            _ = write!(s, "{cell:w$}  ");
+        }
+        out.push_str(s.trim_end());
+        out.push('\n');
+    };
+
+    line(&mut header.iter().copied());
+    for row in &rows {
+        line(&mut row.iter().map(String::as_str));
+    }
+}
+
+impl QueryOutput {
+    pub fn table(&self) -> String {
+        let mut out = String::new();
+
+        let rows = (self.checks.iter())
+            .map(|c| {
+                vec![
+                    c.idx.to_string(),
+                    datetime(c.timestamp_start),
+                    datetime(c.timestamp_end),
+                    format!("{}/{}", c.repos.len(), c.total),
+                    c.repos.join(" "),
+                ]
+            })
+            .collect();
+        let header = ["idx", "start", "end", "matched", "repos"];
+        table(&mut out, "Checks", &header, rows);
+        out.push('\n');
+
+        let rows = (self.repos.iter())
+            .map(|r| {
+                let commit = &r.latest_commit;
+                vec![
+                    format!("{}/{}", r.user, r.repo),
+                    r.sha.clone(),
+                    r.branch.clone(),
+                    r.complete.to_string(),
+                    datetime(commit.datetime),
+                    commit.author.clone(),
+                    commit.mes.lines().next().unwrap_or_default().to_owned(),
+                ]
+            })
+            .collect();
+        let header = [
+            "repo",
+            "sha",
+            "branch",
+            "complete",
+            "committed",
+            "author",
+            "message",
+        ];
+        table(&mut out, "Repos", &header, rows);
+        out.push('\n');
+
+        let rows = (self.repos.iter())
+            .flat_map(|r| r.caches.iter().map(move |c| (r, c)))
+            .map(|(r, c)| {
+                let (timestamp, duration, diagnostics) = match &c.value {
+                    Some(v) => (
+                        datetime(v.timestamp),
+                        v.duration_ms.to_string(),
+                        v.diagnostics.to_string(),
+                    ),
+                    None => ("missing".to_owned(), "-".to_owned(), "-".to_owned()),
+                };
+                vec![
+                    format!("{}/{}", r.user, r.repo),
+                    c.pkg.clone(),
+                    c.checker.as_str().to_owned(),
+                    c.target.clone(),
+                    c.channel.clone(),
+                    timestamp,
+                    duration,
+                    diagnostics,
+                    c.version.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+        let header = [
+            "repo",
+            "pkg",
+            "checker",
+            "target",
+            "channel",
+            "cached",
+            "duration_ms",
+            "diagnostics",
+            "version",
+        ];
+        table(&mut out, "Caches", &header, rows);
+
+        out
+    }
+}
+
+#[test]
+fn query_cache() -> Result<()> {
+    use os_checker_types::{
+        cache::{CacheChecker, CacheCmd, CacheRepoKeyCmd, OutputData},
+        config::RepoConfig,
+        info::{Committer, LatestCommit},
+    };
+
+    let path = std::env::temp_dir().join(format!("os-checker-query-{}.redb", std::process::id()));
+    let path = camino::Utf8Path::from_path(&path).unwrap();
+    let db = Db::new(path)?;
+
+    let repo = |name: &str| CacheRepo {
+        user: "user".into(),
+        repo: name.into(),
+        sha: "0123456789abcdef".into(),
+        branch: "main".into(),
+    };
+    let cache_key = |name: &str, checker| CacheRepoKey {
+        repo: repo(name),
+        cmd: CacheRepoKeyCmd {
+            pkg_name: name.into(),
+            checker: CacheChecker {
+                checker,
+                version: Some("1.0.0".into()),
+                sha: None,
+            },
+            cmd: CacheCmd {
+                cmd: "cargo check".into(),
+                target: "x86_64-unknown-linux-gnu".into(),
+                channel: "nightly".into(),
+                env: Default::default(),
+                features: vec![],
+                flags: vec![],
+            },
+        },
+    };
+    let committer = || Committer {
+        datetime: 0,
+        email: String::new(),
+        name: "author".into(),
+    };
+
+    db.new_check()?;
+    for name in ["a", "b"] {
+        let info_key = InfoKey {
+            repo: repo(name),
+            config: RepoConfig::default(),
+        };
+        let caches = vec![
+            cache_key(name, CheckerTool::Clippy),
+            cache_key(name, CheckerTool::Fmt),
+        ];
+        for key in &caches {
+            let value = CacheValue {
+                unix_timestamp_milli: 0,
+                cmd: key.cmd.clone(),
+                diagnostics: OutputData {
+                    duration_ms: 42,
+                    data: vec![],
+                },
+            };
+            db.set_cache(key, &value)?;
+        }
+        let info = Info {
+            complete: true,
+            caches,
+            latest_commit: LatestCommit {
+                sha: "0123456789abcdef".into(),
+                mes: "init\n\nbody".into(),
+                author: committer(),
+                committer: committer(),
+            },
+        };
+        db.set_info(&info_key, &info)?;
+        db.check_push_info_key(info_key)?;
+    }
+    db.check_set_complete()?;
+
+    let query = Query {
+        repo: Some("a".into()),
+        checker: Some(CheckerTool::Clippy),
+        ..Default::default()
+    };
+    let output = db.query(&query)?;
+    assert_eq!(output.checks[0].repos, ["user/a@01234567"]);
+    assert_eq!(output.checks[0].total, 2);
+    let table = output.table();
+    let (_, table) = table.split_once("\n\n").unwrap();
+    expect_test::expect![[r#"
+        Repos (1)
+        repo    sha               branch  complete  committed  author  message
+        user/a  0123456789abcdef  main    true      -          author  init
+
+        Caches (1)
+        repo    pkg  checker  target                    channel  cached  duration_ms  diagnostics  version
+        user/a  a    clippy   x86_64-unknown-linux-gnu  nightly  -       42           0            1.0.0
+    "#]]
+    .assert_eq(table);
+
+    let query = Query {
+        sha: Some("fff".into()),
+        ..Default::default()
+    };
+    let output = db.query(&query)?;
+    assert!(output.checks.is_empty() && output.repos.is_empty());
+
+    drop(db);
+    std::fs::remove_file(path)?;
+    Ok(())
+}
@@ -0,0 +1,317 @@
+//! 导出、导入与合并 redb 缓存。
+//!
+//! 并行的检查批次可以各自写入独立的缓存文件，最后再合并为一个，而无需竞争同一个文件。
+
+use super::Db;
+use crate::Result;
+use camino::Utf8Path;
+use eyre::Context;
+use os_checker_types::db::{
+    CacheLayout, CacheRepoKey, CacheValue, CheckValue, Info, InfoKey, Record, CHECKS, DATA, INFO,
+    LAYOUT,
+};
+use redb::{
+    Database, ReadableTable, ReadableTableMetadata, Table, TableError, Value, WriteTransaction,
+};
+use std::io::{BufRead, Write};
+
+impl Db {
+    /// 把所有表导出为 JSON lines，每行一个 [`Record`]。返回导出的行数。
+    pub fn export(&self, mut writer: impl Write) -> Result<usize> {
+        let mut count = 0;
+        for_each_record(self.database(), |record| {
+            serde_json::to_writer(&mut writer, &record)?;
+            writer.write_all(b"\n")?;
+            count += 1;
+            Ok(())
+        })?;
+        writer.flush()?;
+        info!(count, "Successfully exported records.");
+        Ok(count)
+    }
+
+    /// 导入由 [`Db::export`] 导出的 JSON lines，合并规则与 [`Db::merge`] 相同。返回导入的行数。
+    pub fn import(&self, reader: impl BufRead) -> Result<usize> {
+        let txn = self.database().begin_write()?;
+        let mut merger = Merger::new(&txn)?;
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line)
+                .with_context(|| format!("Line {} is not a valid record.", idx + 1))?;
+            merger.push(record)?;
+        }
+        let count = merger.finish()?;
+        txn.commit()?;
+        info!(count, "Successfully imported records.");
+        Ok(count)
+    }
+
+    /// 把另一个 redb 文件合并进来：
+    /// * DATA 中相同的键保留时间戳较新的检查结果
+    /// * INFO 中相同的键合并缓存列表
+    /// * LAYOUT 中相同的键意味着相同的仓库提交和配置，保留任一即可
+    /// * CHECKS 按开始时间排序后，时间重叠的检查记录（来自同一次运行的并行批次）合并为一个，
+    ///   键相同的相邻检查记录只保留后者，最后从 0 开始重新编号
+    pub fn merge(&self, other: &Utf8Path) -> Result<usize> {
+        let other =
+            Database::open(other).with_context(|| format!("无法打开 redb 数据库文件 {other}"))?;
+        let txn = self.database().begin_write()?;
+        let mut merger = Merger::new(&txn)?;
+        for_each_record(&other, |record| merger.push(record))?;
+        let count = merger.finish()?;
+        txn.commit()?;
+        info!(count, "Successfully merged records.");
+        Ok(count)
+    }
+}
+
+/// 按 CHECKS、INFO、LAYOUT、DATA 的顺序遍历所有行；不存在的表被跳过。
+fn for_each_record(db: &Database, mut f: impl FnMut(Record) -> Result<()>) -> Result<()> {
+    let txn = db.begin_read()?;
+    macro_rules! each {
+        ($table:ident, $variant:ident) => {
+            match txn.open_table($table) {
+                Ok(table) => {
+                    for item in table.iter()? {
+                        let (key, value) = item?;
+                        f(Record::$variant {
+                            key: key.value(),
+                            value: value.value(),
+                        })?;
+                    }
+                }
+                Err(TableError::TableDoesNotExist(_)) => (),
+                Err(err) => return Err(err.into()),
+            }
+        };
+    }
+    each!(CHECKS, Checks);
+    each!(INFO, Info);
+    each!(LAYOUT, Layout);
+    each!(DATA, Data);
+    Ok(())
+}
+
+struct Merger<'txn> {
+    txn: &'txn WriteTransaction,
+    data: Table<'txn, CacheRepoKey, CacheValue>,
+    info: Table<'txn, InfoKey, Info>,
+    layout: Table<'txn, InfoKey, CacheLayout>,
+    checks: Vec<CheckValue>,
+    count: usize,
+}
+
+impl<'txn> Merger<'txn> {
+    fn new(txn: &'txn WriteTransaction) -> Result<Self> {
+        Ok(Merger {
+            txn,
+            data: txn.open_table(DATA)?,
+            info: txn.open_table(INFO)?,
+            layout: txn.open_table(LAYOUT)?,
+            checks: Vec::new(),
+            count: 0,
+        })
+    }
+
+    fn push(&mut self, record: Record) -> Result<()> {
+        self.count += 1;
+        match record {
+            Record::Checks { value, .. } => self.checks.push(value),
+            Record::Data { key, value } => {
+                let newer = match self.data.get(&key)? {
+                    Some(old) => old.value().unix_timestamp_milli < value.unix_timestamp_milli,
+                    None => true,
+                };
+                if newer {
+                    self.data.insert(&key, &value)?;
+                }
+            }
+            Record::Info { key, value } => {
+                let old = self.info.get(&key)?.map(|guard| guard.value());
+                let value = match old {
+                    Some(old) => merge_info(old, value),
+                    None => value,
+                };
+                self.info.insert(&key, &value)?;
+            }
+            Record::Layout { key, value } => {
+                if self.layout.get(&key)?.is_none() {
+                    self.layout.insert(&key, &value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 与已有的检查记录合并，并重写 CHECKS 表。
+    fn finish(self) -> Result<usize> {
+        let Merger {
+            txn,
+            data,
+            info,
+            layout,
+            mut checks,
+            count,
+        } = self;
+        drop((data, info, layout));
+
+        let mut table = txn.open_table(CHECKS)?;
+        for item in table.iter()? {
+            checks.push(item?.1.value());
+        }
+        let checks = merge_checks(checks);
+        table.retain(|_, _| false)?;
+        for (key, check) in (0u32..).zip(&checks) {
+            table.insert(key, check)?;
+        }
+        info!(len = table.len()?, "Successfully rewrote {CHECKS}.");
+        Ok(count)
+    }
+}
+
+fn merge_info(old: Info, new: Info) -> Info {
+    let mut caches = old.caches;
+    for key in new.caches {
+        let bytes = CacheRepoKey::as_bytes(&key);
+        if !caches.iter().any(|k| CacheRepoKey::as_bytes(k) == bytes) {
+            caches.push(key);
+        }
+    }
+    Info {
+        complete: old.complete || new.complete,
+        caches,
+        latest_commit: new.latest_commit,
+    }
+}
+
+/// 键的比较方式与 redb 一致，即比较编码后的字节。
+fn merge_checks(mut checks: Vec<CheckValue>) -> Vec<CheckValue> {
+    checks.sort_by_key(|check| check.timestamp_start);
+    let mut merged: Vec<CheckValue> = Vec::with_capacity(checks.len());
+    for check in checks {
+        match merged.last_mut() {
+            // 与 check_set_complete 一致：检查相同仓库的相邻记录只保留后者；完全相同的记录也因此去重
+            Some(last) if last.is_same_keys(&check) => *last = check,
+            // 时间重叠的已完成记录来自并行的批次
+            Some(last)
+                if last.timestamp_end != 0 && check.timestamp_start <= last.timestamp_end =>
+            {
+                union_check(last, check)
+            }
+            _ => merged.push(check),
+        }
+    }
+    merged
+}
+
+fn union_check(last: &mut CheckValue, check: CheckValue) {
+    last.timestamp_end = match check.timestamp_end {
+        // 任一批次未完成，则整体未完成
+        0 => 0,
+        end => last.timestamp_end.max(end),
+    };
+    for key in check.keys {
+        let pos = (last.keys.iter()).position(|k| k.info.user_repo() == key.info.user_repo());
+        match pos {
+            // the later check wins
+            Some(pos) => last.keys[pos] = key,
+            None => last.keys.push(key),
+        }
+    }
+    last.keys
+        .sort_unstable_by(|a, b| a.info.repo.cmp(&b.info.repo));
+}
+
+#[test]
+fn merge_parallel_batches() -> Result<()> {
+    use os_checker_types::{cache::CacheRepo, checks::Keys, config::RepoConfig};
+
+    let key = |name: &str| Keys {
+        info: InfoKey {
+            repo: CacheRepo {
+                user: "user".into(),
+                repo: name.into(),
+                sha: "sha".into(),
+                branch: "main".into(),
+            },
+            config: RepoConfig::default(),
+        },
+    };
+    let check = |names: &[&str], timestamp_start, timestamp_end| CheckValue {
+        keys: names.iter().map(|name| key(name)).collect(),
+        timestamp_start,
+        timestamp_end,
+    };
+    let repos = |check: &CheckValue| {
+        let v: Vec<_> = check.keys.iter().map(|k| k.info.user_repo()[1]).collect();
+        v.join(",")
+    };
+
+    let merged = merge_checks(vec![
+        // batch 2 of the first run
+        check(&["c", "d"], 15, 30),
+        // batch 1 of the first run
+        check(&["a", "b"], 10, 20),
+        // the second run checks the same repos in a single batch
+        check(&["a", "b", "c", "d"], 100, 200),
+        // the same check item is from two db files
+        check(&["e"], 300, 400),
+        check(&["e"], 300, 400),
+        // in progress
+        check(&["f"], 500, 0),
+    ]);
+    let merged: Vec<_> = merged
+        .iter()
+        .map(|c| format!("{} [{}, {}]", repos(c), c.timestamp_start, c.timestamp_end))
+        .collect();
+    expect_test::expect![[r#"
+        [
+            "a,b,c,d [100, 200]",
+            "e [300, 400]",
+            "f [500, 0]",
+        ]
+    "#]]
+    .assert_debug_eq(&merged);
+    Ok(())
+}
+
+#[test]
+fn export_import_roundtrip() -> Result<()> {
+    use os_checker_types::{cache::CacheRepo, config::RepoConfig};
+
+    let dir = std::env::temp_dir();
+    let path = |name: &str| {
+        let path = dir.join(format!("os-checker-{name}-{}.redb", std::process::id()));
+        camino::Utf8PathBuf::from_path_buf(path).unwrap()
+    };
+    let [a, b] = [path("export"), path("import")];
+
+    let db = Db::new(&a)?;
+    db.new_check()?;
+    db.check_push_info_key(InfoKey {
+        repo: CacheRepo {
+            user: "user".into(),
+            repo: "repo".into(),
+            sha: "sha".into(),
+            branch: "main".into(),
+        },
+        config: RepoConfig::default(),
+    })?;
+    db.check_set_complete()?;
+    let mut exported = Vec::new();
+    assert_eq!(db.export(&mut exported)?, 1);
+
+    let imported = Db::new(&b)?;
+    assert_eq!(imported.import(exported.as_slice())?, 1);
+    let mut reexported = Vec::new();
+    imported.export(&mut reexported)?;
+    assert_eq!(String::from_utf8(exported)?, String::from_utf8(reexported)?);
+
+    drop((db, imported));
+    std::fs::remove_file(a)?;
+    std::fs::remove_file(b)?;
+    Ok(())
+}
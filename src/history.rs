@@ -0,0 +1,240 @@
+//! Check a single repo at each commit in a range, and find when a diagnostic was introduced.
+//!
+//! Each commit is checked like `os-checker run` with meta.rev set to its sha, so the results
+//! are cached per sha in the redb file, and rerunning over the same range is cheap.
+
+use crate::{
+    config::{CheckerTool, Config},
+    output::JsonOutput,
+    run_checker::RepoOutput,
+    Result,
+};
+use cargo_metadata::camino::Utf8Path;
+use duct::cmd;
+use eyre::ContextCompat;
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::collections::HashSet;
+
+pub struct HistoryArgs<'a> {
+    /// 最早的提交，它作为基准也会被检查
+    pub from: &'a str,
+    /// 最新的提交
+    pub to: &'a str,
+    /// 每 N 个提交检查一次；最新的提交总是被检查
+    pub step: usize,
+    /// 只运行这些检查工具；为空时按照配置运行
+    pub checkers: &'a [CheckerTool],
+    pub fingerprint: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct History {
+    pub user: String,
+    pub repo: String,
+    pub from: String,
+    pub to: String,
+    pub step: usize,
+    /// 按提交顺序从旧到新
+    pub commits: Vec<HistoryCommit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub introduced: Option<Introduced>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryCommit {
+    pub sha: String,
+    pub subject: String,
+    /// 检查结果来自缓存
+    pub cached: bool,
+    pub total: usize,
+    /// kind => count
+    pub kinds: IndexMap<&'static str, usize>,
+    #[serde(skip)]
+    fingerprints: HashSet<String>,
+}
+
+impl HistoryCommit {
+    fn new(sha: String, subject: String, cached: bool, json: &JsonOutput) -> Self {
+        let mut kinds = IndexMap::<_, usize>::new();
+        let mut fingerprints = HashSet::new();
+        for data in &json.data {
+            let kind = os_checker_types::Kind::from(data.kind).as_str();
+            *kinds.entry(kind).or_default() += 1;
+            fingerprints.insert(data.fingerprint.clone());
+        }
+        kinds.sort_unstable_keys();
+        HistoryCommit {
+            sha,
+            subject,
+            cached,
+            total: json.data.len(),
+            kinds,
+            fingerprints,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Introduced {
+    pub fingerprint: String,
+    /// 最后一段连续出现该诊断的第一个提交；None 表示所有提交都不含该诊断
+    pub commit: Option<String>,
+    /// commit 之前最近一个不含该诊断的已检查提交：step 大于 1 时，引入该诊断的提交位于
+    /// (previous, commit] 之间，可以用 `--step 1` 检查这个范围；None 表示在基准提交中已经存在
+    pub previous: Option<String>,
+    /// 最新的提交是否仍然含有该诊断
+    pub present: bool,
+}
+
+fn introduced(commits: &[HistoryCommit], fingerprint: &str) -> Introduced {
+    let has = |c: &HistoryCommit| c.fingerprints.contains(fingerprint);
+    let (commit, previous) = match commits.iter().rposition(has) {
+        Some(last) => {
+            let start = commits[..last]
+                .iter()
+                .rposition(|c| !has(c))
+                .map_or(0, |idx| idx + 1);
+            let previous = start.checked_sub(1).map(|idx| commits[idx].sha.clone());
+            (Some(commits[start].sha.clone()), previous)
+        }
+        None => (None, None),
+    };
+    Introduced {
+        fingerprint: fingerprint.to_owned(),
+        commit,
+        previous,
+        present: commits.last().is_some_and(has),
+    }
+}
+
+pub fn run(mut config: Config, args: &HistoryArgs) -> Result<History> {
+    let _span = error_span!("history", repo = config.key()).entered();
+    ensure!(
+        !config.is_local(),
+        "history doesn't support the local repo {}; use a git url instead",
+        config.key()
+    );
+    ensure!(args.step > 0, "--step should be at least 1");
+    if !args.checkers.is_empty() {
+        config.select_checkers(args.checkers);
+    }
+
+    let root = config.clone().local_root_path_with_git_clone()?;
+    let shallow = cmd!("git", "rev-parse", "--is-shallow-repository")
+        .dir(&root)
+        .read()?;
+    ensure!(
+        shallow.trim() != "true",
+        "history needs the full git history; remove meta.clone.depth for {}",
+        config.key()
+    );
+    let commits = every_nth(list_commits(&root, args.from, args.to)?, args.step);
+    info!(len = commits.len(), "commits to check");
+
+    let mut v = Vec::with_capacity(commits.len());
+    for (idx, (sha, subject)) in commits.into_iter().enumerate() {
+        let _span = error_span!("commit", idx, sha).entered();
+        let mut config = config.clone();
+        config.set_checkout(&sha);
+        let out = RepoOutput::run(config)?;
+        let json = JsonOutput::new(std::slice::from_ref(&out));
+        let commit = HistoryCommit::new(sha, subject, out.is_right(), &json);
+        info!(commit.total, commit.cached, ?commit.kinds);
+        v.push(commit);
+    }
+
+    Ok(History {
+        user: config.user_name().to_owned(),
+        repo: config.repo_name().to_owned(),
+        from: args.from.to_owned(),
+        to: args.to.to_owned(),
+        step: args.step,
+        introduced: args.fingerprint.map(|fp| introduced(&v, fp)),
+        commits: v,
+    })
+}
+
+/// 返回 `from` 以及 `from..to` 中 first-parent 上的所有提交的 sha 和标题，从旧到新。
+fn list_commits(root: &Utf8Path, from: &str, to: &str) -> Result<Vec<(String, String)>> {
+    const FORMAT: &str = "--format=%H%x09%s";
+    let base = cmd!("git", "log", "-1", FORMAT, from).dir(root).read()?;
+    let range = format!("{from}..{to}");
+    let log = cmd!("git", "log", "--reverse", "--first-parent", FORMAT, range)
+        .dir(root)
+        .read()?;
+    base.lines()
+        .chain(log.lines())
+        .map(|line| {
+            let (sha, subject) = line
+                .split_once('\t')
+                .with_context(|| format!("{line:?} is not in the format of `sha\\tsubject`"))?;
+            Ok((sha.to_owned(), subject.to_owned()))
+        })
+        .collect()
+}
+
+fn every_nth<T>(commits: Vec<T>, step: usize) -> Vec<T> {
+    let last = commits.len().saturating_sub(1);
+    commits
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| idx % step == 0 || *idx == last)
+        .map(|(_, commit)| commit)
+        .collect()
+}
+
+#[cfg(test)]
+fn commit(sha: &str, fingerprints: &[&str]) -> HistoryCommit {
+    HistoryCommit {
+        sha: sha.to_owned(),
+        subject: String::new(),
+        cached: false,
+        total: fingerprints.len(),
+        kinds: IndexMap::new(),
+        fingerprints: fingerprints.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+#[test]
+fn step_commits() {
+    let v: Vec<_> = (0..8).collect();
+    assert_eq!(every_nth(v.clone(), 1), v);
+    assert_eq!(every_nth(v.clone(), 3), [0, 3, 6, 7]);
+    assert_eq!(every_nth(v, 10), [0, 7]);
+    assert!(every_nth(Vec::<u8>::new(), 2).is_empty());
+}
+
+#[test]
+fn introduced_commit() {
+    let commits = [
+        commit("a", &[]),
+        commit("b", &["x"]),
+        commit("c", &["x", "y"]),
+        commit("d", &["y"]),
+        commit("e", &["x", "y"]),
+    ];
+    let found = |fp| {
+        let Introduced {
+            commit,
+            previous,
+            present,
+            ..
+        } = introduced(&commits, fp);
+        (commit, previous, present)
+    };
+    let some = |s: &str| Some(s.to_owned());
+    // x is fixed in d, and introduced again in e
+    assert_eq!(found("x"), (some("e"), some("d"), true));
+    assert_eq!(found("y"), (some("c"), some("b"), true));
+    assert_eq!(found("z"), (None, None, false));
+
+    let commits = [commit("a", &["x"]), commit("b", &[])];
+    let Introduced {
+        commit,
+        previous,
+        present,
+        ..
+    } = introduced(&commits, "x");
+    assert_eq!((commit, previous, present), (some("a"), None, false));
+}
@@ -7,19 +7,25 @@
 //! So here are the steps to do audit check:
 //! * for each workspace root dir, see if there is a Cargo.lock,
 //! * if not, call cargo-generate-lockfile to get one,
-//! * call cargo-audit with and without --json to get the results
-//!   * both results will be displayed on WebUI
+//! * call cargo-audit with --json to get the results
 //!   * json result helps to identify the problematic dependencies,
 //!     and we search each pkg denpendencies resolution for the
 //!     problematic denpendencies,
+//!   * each advisory or warning is emitted as a diagnostic along with
+//!     the dependency path from the pkg to the problematic dependency,
+//!   * if cargo-audit fails, the error is emitted as a Cargo diagnostic,
 //! * target is not used with cargo-audit, so the pkg audit result
 //!   will repeat for each target.
 
-use crate::{utils::cmd_run_with, Result, XString};
+use crate::{
+    cli::{advisory_db, advisory_db_no_fetch, offline},
+    Result, XString,
+};
 use camino::{Utf8Path, Utf8PathBuf};
 use duct::cmd;
 use eyre::Context;
-use indexmap::{IndexMap, IndexSet};
+use indexmap::IndexMap;
+use os_checker_types::Severity;
 use rustsec::{
     cargo_lock::{
         dependency::graph::{Graph, NodeIndex, Nodes},
@@ -27,60 +33,232 @@ use rustsec::{
     },
     Report,
 };
-use std::rc::Rc;
+use std::{collections::VecDeque, fmt::Write, rc::Rc, sync::LazyLock};
 
 #[instrument(level = "info")]
 fn generate_lockfile(workspace_dir: &Utf8Path) -> Result<()> {
-    _ = cmd!("cargo", "generate-lockfile")
-        .dir(workspace_dir)
-        .run()?;
+    let mut args = vec!["generate-lockfile"];
+    if offline() {
+        args.push("--offline");
+    }
+    _ = duct::cmd("cargo", args).dir(workspace_dir).run()?;
     Ok(())
 }
 
-#[allow(dead_code)]
 pub struct CargoAudit {
-    problematic_pkgs: Vec<XString>,
-    tree: String,
-    json: String,
-    output: String,
-    /// parsed from json
-    report: Report,
+    /// Problematic local pkgs and the dependency paths to each finding.
+    pkg_paths: IndexMap<XString, Vec<FindingPath>>,
+    /// Vulnerabilities and warnings from the report.
+    findings: Vec<Finding>,
     lock_file: Utf8PathBuf,
+    /// cargo-audit itself might fail due to like Cargo.lock format version changes.
+    /// See https://github.com/os-checker/os-checker/issues/42#issuecomment-2408453064
+    error: Option<String>,
+    /// The commit of advisory-db used in auditing, which is part of the cache key.
+    db_commit: Option<String>,
 }
 
 impl std::fmt::Debug for CargoAudit {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CargoAudit")
-            .field("problematic_pkgs", &self.problematic_pkgs)
-            .field("tree", &self.tree)
+            .field("problematic_pkgs", &self.pkg_paths.keys())
             .field("lock_file", &self.lock_file)
+            .field("error", &self.error)
             .finish()
     }
 }
 
+/// A vulnerability or warning reported by cargo-audit.
+#[derive(Debug)]
+struct Finding {
+    /// Advisory id, or the warning kind if there is no advisory like yanked crates.
+    id: String,
+    /// vulnerability, or the warning kind like unmaintained, unsound and yanked
+    kind: String,
+    /// Only available from the CVSS of a vulnerability.
+    severity: Option<String>,
+    package: Dependency,
+    title: String,
+    patched: Vec<String>,
+}
+
+impl Finding {
+    fn from_report(report: &Report) -> Vec<Self> {
+        let vulnerabilities = report.vulnerabilities.list.iter().map(|vul| Finding {
+            id: vul.advisory.id.to_string(),
+            kind: "vulnerability".to_owned(),
+            severity: vul.advisory.cvss.as_ref().map(|c| c.severity().to_string()),
+            package: Dependency::from(&vul.package),
+            title: vul.advisory.title.clone(),
+            patched: vul
+                .versions
+                .patched()
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
+        });
+        let warnings = report.warnings.values().flatten().map(|w| Finding {
+            id: w
+                .advisory
+                .as_ref()
+                .map(|a| a.id.to_string())
+                .unwrap_or_else(|| w.kind.as_str().to_owned()),
+            kind: w.kind.as_str().to_owned(),
+            severity: None,
+            package: Dependency::from(&w.package),
+            title: w
+                .advisory
+                .as_ref()
+                .map(|a| a.title.clone())
+                .unwrap_or_else(|| format!("{} is {}", w.package.name, w.kind.as_str())),
+            patched: w
+                .versions
+                .iter()
+                .flat_map(|v| v.patched().iter().map(|v| v.to_string()))
+                .collect(),
+        });
+        vulnerabilities.chain(warnings).collect()
+    }
+
+    fn diagnostic(&self, path: &[String]) -> AuditDiagnostic {
+        let severity = if self.kind == "vulnerability" {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        let help =
+            (!self.patched.is_empty()).then(|| format!("upgrade to {}", self.patched.join(" or ")));
+        AuditDiagnostic {
+            id: self.id.clone(),
+            severity,
+            package: (
+                self.package.name.to_string(),
+                self.package.version.to_string(),
+            ),
+            help,
+            raw: self.render(path),
+        }
+    }
+
+    fn render(&self, path: &[String]) -> String {
+        let Self {
+            id,
+            kind,
+            severity,
+            package,
+            title,
+            patched,
+        } = self;
+        let mut buf = String::with_capacity(256);
+        _ = writeln!(buf, "{id}: {title}");
+        _ = write!(buf, "kind: {kind}");
+        if let Some(severity) = severity {
+            _ = write!(buf, ", severity: {severity}");
+        }
+        _ = writeln!(buf, "\npackage: {}@{}", package.name, package.version);
+        if patched.is_empty() {
+            _ = writeln!(buf, "patched: none");
+        } else {
+            _ = writeln!(buf, "patched: {}", patched.join(", "));
+        }
+        _ = write!(buf, "dependency path: {}", path.join(" -> "));
+        buf
+    }
+}
+
+/// A finding reachable from a local pkg.
+#[derive(Debug)]
+struct FindingPath {
+    /// index into `CargoAudit::findings`
+    finding: usize,
+    /// `name@version` from the local pkg to the problematic dependency
+    path: Vec<String>,
+}
+
+/// Per-pkg diagnostics from cargo-audit.
+#[derive(Debug)]
+pub struct AuditDiagnostics {
+    pub lock_file: Utf8PathBuf,
+    /// One diagnostic per finding.
+    pub findings: Box<[AuditDiagnostic]>,
+}
+
+#[derive(Debug)]
+pub struct AuditDiagnostic {
+    /// Advisory id, or the warning kind if there is no advisory.
+    pub id: String,
+    /// Vulnerabilities are errors, and others like unmaintained or yanked are warnings.
+    pub severity: Severity,
+    /// The problematic dependency as `(name, version)`.
+    pub package: (String, String),
+    /// Patched versions to upgrade to if any.
+    pub help: Option<String>,
+    pub raw: String,
+}
+
 impl CargoAudit {
     pub fn is_problematic(&self) -> bool {
-        !self.problematic_pkgs.is_empty()
+        !self.pkg_paths.is_empty() || self.error.is_some()
     }
 
-    // FIXME: There might possibily be a Cargo error output, because cargo-audit itself
-    // might fail due to like Cargo.lock format version changes.
-    // So it'd be better to emit normal output or Cargo output.
-    // See https://github.com/os-checker/os-checker/issues/42#issuecomment-2408453064
-    pub fn output(&self) -> &str {
-        &self.output
+    /// Emit one diagnostic per advisory or warning that the pkg depends on.
+    /// Returns an error if cargo-audit fails.
+    pub fn diagnostics(&self, pkg_name: &str) -> Result<AuditDiagnostics> {
+        if let Some(err) = &self.error {
+            bail!("cargo-audit fails on {}:\n{err}", self.lock_file);
+        }
+        let findings = self
+            .pkg_paths
+            .get(pkg_name)
+            .map(|paths| {
+                paths
+                    .iter()
+                    .map(|p| self.findings[p.finding].diagnostic(&p.path))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(AuditDiagnostics {
+            lock_file: self.lock_file.clone(),
+            findings,
+        })
     }
 
     pub fn lock_file(&self) -> &Utf8Path {
         &self.lock_file
     }
 
-    pub fn new(workspace_dir: &Utf8Path) -> Result<Rc<Self>> {
-        cargo_audit(workspace_dir).map(Rc::new)
+    pub fn db_commit(&self) -> Option<&str> {
+        self.db_commit.as_deref()
+    }
+
+    /// cargo-audit fails to run or its result fails to be parsed,
+    /// but the failure should be reported as a diagnostic.
+    fn new(workspace_dir: &Utf8Path, pkgs: &[(XString, Utf8PathBuf)]) -> Rc<Self> {
+        let audit = cargo_audit(workspace_dir).unwrap_or_else(|err| {
+            error!(?err, %workspace_dir, "cargo-audit fails");
+            let pkg_paths = pkgs
+                .iter()
+                .filter(|(_, pkg_dir)| pkg_dir.starts_with(workspace_dir))
+                .map(|(name, _)| (name.clone(), Vec::new()))
+                .collect();
+            CargoAudit {
+                pkg_paths,
+                findings: Vec::new(),
+                lock_file: workspace_dir.join("Cargo.lock"),
+                error: Some(format!("{err:?}")),
+                db_commit: None,
+            }
+        });
+        Rc::new(audit)
     }
 
     pub fn cmd(&self) -> String {
-        "cargo audit".to_owned()
+        let mut cmd = "cargo audit".to_owned();
+        for arg in advisory_db_args() {
+            cmd.push(' ');
+            cmd.push_str(&arg);
+        }
+        cmd
     }
 
     /// NOTE: this is not meaningful because cargo-audit only needs
@@ -88,23 +266,95 @@ impl CargoAudit {
     pub fn cmd_expr(&self) -> duct::Expression {
         let mut path = self.lock_file.clone();
         path.pop();
-        cmd!("cargo", "audit").dir(path).env("RUST_LOG", "off")
+        let mut args = vec!["audit".to_owned()];
+        args.extend(advisory_db_args());
+        duct::cmd("cargo", args).dir(path).env("RUST_LOG", "off")
     }
 
-    /// returns the map where the key is pkg name and the value is audit result
-    pub fn new_for_pkgs(dirs: Vec<Utf8PathBuf>) -> Result<IndexMap<XString, Rc<Self>>> {
+    /// returns the map where the key is pkg name and the value is audit result;
+    /// pkgs are pairs of pkg name and dir, used to attribute cargo-audit failures
+    pub fn new_for_pkgs(
+        dirs: Vec<Utf8PathBuf>,
+        pkgs: &[(XString, Utf8PathBuf)],
+    ) -> IndexMap<XString, Rc<Self>> {
         let mut map = IndexMap::new();
         for dir in dirs {
-            let audit = Self::new(&dir)?;
-            for pkg in &audit.problematic_pkgs {
+            let audit = Self::new(&dir, pkgs);
+            for pkg in audit.pkg_paths.keys() {
                 // NOTE: there is supposed to be no pkg name aliasing.
                 map.insert(pkg.clone(), audit.clone());
             }
         }
-        Ok(map)
+        map
+    }
+}
+
+/// Extra cargo-audit arguments for `--advisory-db` and `--advisory-db-no-fetch`.
+fn advisory_db_args() -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(db) = advisory_db() {
+        args.extend(["--db".to_owned(), db.into_string()]);
+    }
+    if advisory_db_no_fetch() {
+        args.push("--no-fetch".to_owned());
+    }
+    args
+}
+
+/// The advisory-db dir used by cargo-audit: `--advisory-db` or `$CARGO_HOME/advisory-db`.
+pub fn advisory_db_dir() -> Option<Utf8PathBuf> {
+    advisory_db().or_else(|| {
+        let cargo_home = std::env::var("CARGO_HOME")
+            .or_else(|_| std::env::var("HOME").map(|home| format!("{home}/.cargo")))
+            .ok()?;
+        Some(Utf8PathBuf::from(cargo_home).join("advisory-db"))
+    })
+}
+
+/// The HEAD commit of the advisory-db git repo. None if unavailable.
+fn advisory_db_commit() -> Option<String> {
+    let dir = advisory_db_dir()?;
+    match cmd!("git", "rev-parse", "HEAD").dir(&dir).read() {
+        Ok(sha) => Some(sha.trim().to_owned()),
+        Err(err) => {
+            warn!(%dir, ?err, "unable to get the commit of advisory-db");
+            None
+        }
     }
 }
 
+/// The advisory-db commit that cargo-audit audits against in this run: the remote HEAD
+/// if cargo-audit fetches the db, otherwise the local HEAD. None if unavailable.
+///
+/// Cached audit results are reused only if they were audited against this commit.
+pub fn expected_advisory_db_commit() -> Option<String> {
+    static COMMIT: LazyLock<Option<String>> = LazyLock::new(|| {
+        if advisory_db_no_fetch() {
+            return advisory_db_commit();
+        }
+        let url = advisory_db_dir()
+            .filter(|dir| dir.exists())
+            .and_then(|dir| {
+                cmd!("git", "remote", "get-url", "origin")
+                    .dir(dir)
+                    .read()
+                    .ok()
+            })
+            .unwrap_or_else(|| ADVISORY_DB_URL.to_owned());
+        match cmd!("git", "ls-remote", url.trim(), "HEAD").read() {
+            Ok(out) => out.split_whitespace().next().map(String::from),
+            Err(err) => {
+                warn!(url, ?err, "unable to get the latest commit of advisory-db");
+                None
+            }
+        }
+    });
+    COMMIT.clone()
+}
+
+/// The default advisory-db fetched by cargo-audit.
+const ADVISORY_DB_URL: &str = "https://github.com/RustSec/advisory-db.git";
+
 fn cargo_audit(workspace_dir: &Utf8Path) -> Result<CargoAudit> {
     let mut lock_file = workspace_dir.to_owned();
     lock_file.push("Cargo.lock");
@@ -115,65 +365,58 @@ fn cargo_audit(workspace_dir: &Utf8Path) -> Result<CargoAudit> {
         generate_lockfile(workspace_dir)?;
     }
 
-    let json = cmd_run_with(
-        "cargo",
-        &["audit", "--json", "-c", "never"],
-        workspace_dir,
-        true,
-        |cmd| cmd.env("RUST_LOG", "off"),
-    )?;
+    // cargo-audit exits with 1 if vulnerabilities are found, so don't check exit code
+    let mut args = vec![
+        "audit".to_owned(),
+        "--json".to_owned(),
+        "-c".to_owned(),
+        "never".to_owned(),
+    ];
+    args.extend(advisory_db_args());
+    let out = duct::cmd("cargo", args)
+        .dir(workspace_dir)
+        .env("RUST_LOG", "off")
+        .unchecked()
+        .stdout_capture()
+        .stderr_capture()
+        .run()?;
+    let json =
+        String::from_utf8(out.stdout).with_context(|| "stdout contains invalid UTF-8 chars")?;
 
-    let report: rustsec::Report = serde_json::from_str(&json)
-        .with_context(|| format!("Fail to parse json as a rustsec::Report:\n{json}"))?;
+    let report: rustsec::Report = serde_json::from_str(&json).with_context(|| {
+        format!(
+            "Fail to parse json as a rustsec::Report:\n{json}\nstderr={}",
+            String::from_utf8_lossy(&out.stderr)
+        )
+    })?;
+    // read after auditing, since cargo-audit may have fetched the advisory-db
+    let db_commit = advisory_db_commit();
     if !report.vulnerabilities.found && report.warnings.is_empty() {
         return Ok(CargoAudit {
-            problematic_pkgs: vec![],
-            tree: String::new(),
-            json,
-            output: String::new(),
-            report,
+            pkg_paths: IndexMap::new(),
+            findings: Vec::new(),
             lock_file,
+            error: None,
+            db_commit,
         });
     }
 
-    let tree = cmd_run_with(
-        "cargo",
-        &["audit", "-c", "never"],
-        workspace_dir,
-        true,
-        |cmd| cmd.env("RUST_LOG", "off"),
-    )?;
-
-    let mut problematic = IndexSet::<Dependency>::new();
-    let vulnerable = &report.vulnerabilities.list;
-    problematic.extend(vulnerable.iter().map(|vul| Dependency::from(&vul.package)));
-    let warnings = report.warnings.values();
-    problematic.extend(warnings.flat_map(|v| v.iter().map(|w| Dependency::from(&w.package))));
-
-    let problematic_pkgs = parse_cargo_lock(&lock_file, &problematic)?;
-
-    let output = {
-        let json = match jsonxf::pretty_print(&json) {
-            Ok(json) => json,
-            Err(json) => json,
-        };
-        format!("{tree}\n{json}")
-    };
+    let findings = Finding::from_report(&report);
+    let pkg_paths = parse_cargo_lock(&lock_file, &findings)?;
 
     Ok(CargoAudit {
-        problematic_pkgs,
-        tree,
-        json,
-        output,
-        report,
+        pkg_paths,
+        findings,
         lock_file,
+        error: None,
+        db_commit,
     })
 }
 
 fn parse_cargo_lock(
     lock_file: &Utf8Path,
-    problematic: &IndexSet<Dependency>,
-) -> Result<Vec<XString>> {
+    findings: &[Finding],
+) -> Result<IndexMap<XString, Vec<FindingPath>>> {
     let lockfile = rustsec::Lockfile::load(lock_file)?;
 
     let tree = lockfile.dependency_tree()?;
@@ -181,47 +424,77 @@ fn parse_cargo_lock(
     let nodes = tree.nodes();
 
     // suppose local pkgs without source and  checksum
-    let local_pkgs: Vec<_> = lockfile
-        .packages
-        .iter()
-        .filter(|pkg| pkg.source.is_none())
-        .collect();
-    let mut problematic_local_pkgs = Vec::new();
-
-    let mut map = IndexSet::<&Dependency>::new();
-    for pkg in &local_pkgs {
-        map.clear();
-        for dep in &pkg.dependencies {
-            let idx = *nodes.get(dep).unwrap();
-            recursive_dependencies(&mut map, idx, graph, nodes);
-        }
-        // TODO: maybe we could point out which dependencies are problematic,
-        // though they are in output and json.
-        for dep in problematic {
-            // local pkg contains a problematic dependency in the graph
-            if map.contains(&dep) {
-                problematic_local_pkgs.push(XString::from(pkg.name.as_str()));
-            }
+    let local_pkgs = lockfile.packages.iter().filter(|pkg| pkg.source.is_none());
+    let mut problematic_local_pkgs = IndexMap::new();
+
+    for pkg in local_pkgs {
+        let Some(&start) = nodes.get(&Dependency::from(pkg)) else {
+            continue;
+        };
+        let parents = shortest_paths(start, graph, nodes);
+        let paths: Vec<_> = findings
+            .iter()
+            .enumerate()
+            .filter_map(|(finding, f)| {
+                let idx = *nodes.get(&f.package)?;
+                // local pkg contains a problematic dependency in the graph
+                parents.contains_key(&idx).then(|| FindingPath {
+                    finding,
+                    path: rebuild_path(start, idx, &parents, graph),
+                })
+            })
+            .collect();
+        if !paths.is_empty() {
+            problematic_local_pkgs.insert(XString::from(pkg.name.as_str()), paths);
         }
     }
 
     Ok(problematic_local_pkgs)
 }
 
-fn recursive_dependencies<'a>(
-    map: &mut IndexSet<&'a Dependency>,
-    idx: NodeIndex,
-    graph: &'a Graph,
+/// Breadth-first search from the local pkg, recording the parent of each reached
+/// dependency so that the shortest path to it can be rebuilt.
+fn shortest_paths(
+    start: NodeIndex,
+    graph: &Graph,
     nodes: &Nodes,
-) {
-    for (direct_dep, dep_idx) in graph.edges(idx).map(|edge| {
-        let dep = edge.weight();
-        (dep, *nodes.get(dep).unwrap())
-    }) {
-        if map.insert(direct_dep) {
-            recursive_dependencies(map, dep_idx, graph, nodes);
+) -> IndexMap<NodeIndex, NodeIndex> {
+    let mut parents = IndexMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(idx) = queue.pop_front() {
+        for edge in graph.edges(idx) {
+            let dep_idx = *nodes.get(edge.weight()).unwrap();
+            if dep_idx != start && !parents.contains_key(&dep_idx) {
+                parents.insert(dep_idx, idx);
+                queue.push_back(dep_idx);
+            }
+        }
+    }
+    parents
+}
+
+fn rebuild_path(
+    start: NodeIndex,
+    end: NodeIndex,
+    parents: &IndexMap<NodeIndex, NodeIndex>,
+    graph: &Graph,
+) -> Vec<String> {
+    let mut path = vec![end];
+    let mut idx = end;
+    while let Some(&parent) = parents.get(&idx) {
+        path.push(parent);
+        if parent == start {
+            break;
         }
+        idx = parent;
     }
+    path.iter()
+        .rev()
+        .map(|&idx| {
+            let pkg = &graph[idx];
+            format!("{}@{}", pkg.name, pkg.version)
+        })
+        .collect()
 }
 
 #[test]
@@ -237,5 +510,34 @@ fn test_cargo_audit() {
     //     src\utils\mod.rs:98:9
     crate::logger::init();
     let dir = Utf8PathBuf::from_iter(["src", "layout", "tests"]);
-    dbg!(cargo_audit(&dir).unwrap().problematic_pkgs);
+    dbg!(cargo_audit(&dir).unwrap().pkg_paths);
+}
+
+#[test]
+fn dependency_path_to_finding() -> Result<()> {
+    let lockfile = rustsec::Lockfile::load("Cargo.lock")?;
+    let macros = lockfile
+        .packages
+        .iter()
+        .find(|pkg| pkg.name.as_str() == "musli-macros")
+        .unwrap();
+    let finding = Finding {
+        id: "RUSTSEC-0000-0000".to_owned(),
+        kind: "vulnerability".to_owned(),
+        severity: Some("high".to_owned()),
+        package: Dependency::from(macros),
+        title: "test".to_owned(),
+        patched: vec![],
+    };
+    let pkg_paths = parse_cargo_lock(Utf8Path::new("Cargo.lock"), &[finding])?;
+    let path: Vec<_> = pkg_paths["os-checker-types"][0]
+        .path
+        .iter()
+        .map(|s| s.split_once('@').unwrap().0)
+        .collect();
+    assert_eq!(
+        path,
+        ["os-checker-types", "musli", "musli-core", "musli-macros"]
+    );
+    Ok(())
 }
@@ -0,0 +1,213 @@
+use super::Layout;
+use crate::{output::Data, utils::changed_files, Result, XString};
+use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
+use indexmap::{IndexMap, IndexSet};
+
+/// `--since` 模式下的改动文件，以及包含这些改动的 packages。
+#[derive(Debug)]
+pub struct ChangedFiles {
+    root: Utf8PathBuf,
+    /// 改动文件的完整路径
+    files: IndexSet<Utf8PathBuf>,
+    /// pkg_name => pkg_dir：只包含拥有改动文件的 packages
+    pkgs: IndexMap<XString, Utf8PathBuf>,
+    /// 受 Cargo.lock、工具链等全局文件改动影响的 packages：保留它们的所有诊断
+    whole: IndexSet<XString>,
+    /// 保留针对整个 crate 的诊断，即使其文件未被改动
+    keep_crate_wide: bool,
+}
+
+impl ChangedFiles {
+    pub fn new(layout: &Layout, since: &str, keep_crate_wide: bool) -> Result<Self> {
+        let root = layout.repo_root();
+        let files = changed_files(root, since)?;
+        let pkgs = layout
+            .packages_info
+            .iter()
+            .map(|info| (info.pkg_name.as_str(), info.pkg_dir.as_path()));
+        let changed = Self::from_parts(root, files, pkgs, keep_crate_wide);
+        info!(
+            since,
+            files = changed.files.len(),
+            pkgs = ?changed.pkgs.keys().collect::<Vec<_>>(),
+            "changed files"
+        );
+        if changed.pkgs.is_empty() {
+            warn!(
+                since,
+                "no package is selected by the changed files, so nothing is checked"
+            );
+        }
+        Ok(changed)
+    }
+
+    /// files 相对于 root；一个文件只属于包含它的最深的 package 目录，
+    /// 因此 packages 之外的普通文件（比如 docs）不会选中任何 package。
+    /// 但 Cargo.lock、rust-toolchain 以及不属于 package 自身（或者位于仓库根目录）的 Cargo.toml
+    /// 影响其目录下的所有 packages，比如升级依赖或者工具链。
+    fn from_parts<'a>(
+        root: &Utf8Path,
+        files: Vec<Utf8PathBuf>,
+        pkgs: impl Iterator<Item = (&'a str, &'a Utf8Path)>,
+        keep_crate_wide: bool,
+    ) -> Self {
+        let files: IndexSet<_> = files.into_iter().map(|f| root.join(f)).collect();
+        let all: Vec<_> = pkgs.collect();
+        let mut pkgs = IndexMap::new();
+        let mut whole = IndexSet::new();
+        for file in &files {
+            if let Some(dir) = affects_all(root, file, &all) {
+                for (name, pkg_dir) in all.iter().filter(|(_, d)| d.starts_with(dir)) {
+                    pkgs.insert(XString::from(*name), pkg_dir.to_path_buf());
+                    whole.insert(XString::from(*name));
+                }
+                continue;
+            }
+            let owner = all
+                .iter()
+                .filter(|(_, dir)| file.starts_with(dir))
+                .max_by_key(|(_, dir)| dir.components().count());
+            if let Some((name, dir)) = owner {
+                pkgs.insert(XString::from(*name), dir.to_path_buf());
+            }
+        }
+        pkgs.sort_unstable_keys();
+        ChangedFiles {
+            root: root.to_owned(),
+            files,
+            pkgs,
+            whole,
+            keep_crate_wide,
+        }
+    }
+
+    pub fn contains_pkg(&self, pkg_name: &str) -> bool {
+        self.pkgs.contains_key(pkg_name)
+    }
+
+    /// 诊断中的文件路径可能是绝对路径，也可能相对于 package 或者 workspace 目录，
+    /// 因此在 package 目录及其位于仓库内的所有上级目录中查找。
+    fn is_changed(&self, pkg_dir: &Utf8Path, file: &Utf8Path) -> bool {
+        if file.is_absolute() {
+            return self.files.contains(file);
+        }
+        pkg_dir
+            .ancestors()
+            .take_while(|dir| dir.starts_with(&self.root))
+            .any(|dir| self.files.contains(&dir.join(file)))
+    }
+
+    pub fn keep(&self, pkg_name: &str, data: &Data) -> bool {
+        if self.whole.contains(pkg_name) || (self.keep_crate_wide && data.kind.is_crate_wide()) {
+            return true;
+        }
+        match self.pkgs.get(pkg_name) {
+            Some(pkg_dir) => self.is_changed(pkg_dir, &data.file),
+            None => false,
+        }
+    }
+}
+
+/// 如果 file 影响其目录下的所有 packages，返回该目录。
+fn affects_all<'a>(
+    root: &Utf8Path,
+    file: &'a Utf8Path,
+    pkgs: &[(&str, &Utf8Path)],
+) -> Option<&'a Utf8Path> {
+    let dir = file.parent()?;
+    match file.file_name()? {
+        "Cargo.lock" | "rust-toolchain" | "rust-toolchain.toml" => Some(dir),
+        // workspace 的 Cargo.toml：虚拟 manifest 或者位于仓库根目录
+        "Cargo.toml" if dir == root || !pkgs.iter().any(|(_, pkg_dir)| *pkg_dir == dir) => {
+            Some(dir)
+        }
+        _ => None,
+    }
+}
+
+#[test]
+fn changed_pkgs_and_files() {
+    use crate::output::Kind;
+    use os_checker_types::DataDetail;
+
+    let root = Utf8Path::new("/repo");
+    let pkgs = [
+        ("ws", "/repo"),
+        ("a", "/repo/crates/a"),
+        ("a-macros", "/repo/crates/a/macros"),
+        ("b", "/repo/crates/b"),
+    ];
+    let files = ["crates/a/macros/src/lib.rs", "build.rs", "docs/README.md"];
+    let changed = ChangedFiles::from_parts(
+        root,
+        files.map(Utf8PathBuf::from).to_vec(),
+        pkgs.iter().map(|(name, dir)| (*name, Utf8Path::new(dir))),
+        false,
+    );
+    // ws 是仓库根目录的 package，因此拥有根目录下的改动
+    assert_eq!(changed.pkgs.keys().collect::<Vec<_>>(), ["a-macros", "ws"]);
+
+    let data = |file: &str, kind| Data {
+        cmd_idx: 0,
+        file: file.into(),
+        kind,
+        raw: String::new(),
+        fingerprint: String::new(),
+        detail: DataDetail::default(),
+    };
+    let clippy = |file| data(file, Kind::ClippyWarn);
+    // 相对于 package 目录、workspace 目录，以及绝对路径
+    assert!(changed.keep("a-macros", &clippy("src/lib.rs")));
+    assert!(changed.keep("ws", &clippy("crates/a/macros/src/lib.rs")));
+    assert!(changed.keep("ws", &clippy("/repo/build.rs")));
+    assert!(!changed.keep("a-macros", &clippy("src/main.rs")));
+    assert!(!changed.keep("a", &clippy("macros/src/lib.rs")));
+
+    let audit = data("/repo/crates/b/Cargo.lock", Kind::Audit);
+    assert!(!changed.keep("b", &audit));
+    let changed = ChangedFiles {
+        keep_crate_wide: true,
+        ..changed
+    };
+    assert!(changed.keep("b", &audit));
+}
+
+#[test]
+fn workspace_files_select_all_pkgs() {
+    use crate::output::Kind;
+    use os_checker_types::DataDetail;
+
+    // 虚拟 workspace：仓库根目录没有 package
+    let root = Utf8Path::new("/repo");
+    let pkgs = [("a", "/repo/crates/a"), ("b", "/repo/crates/b")];
+    let changed = |files: &[&str]| {
+        ChangedFiles::from_parts(
+            root,
+            files.iter().map(Utf8PathBuf::from).collect(),
+            pkgs.iter().map(|(name, dir)| (*name, Utf8Path::new(dir))),
+            false,
+        )
+    };
+    fn selected(c: &ChangedFiles) -> Vec<&str> {
+        c.pkgs.keys().map(|s| s.as_str()).collect()
+    }
+
+    for file in ["Cargo.lock", "Cargo.toml", "rust-toolchain.toml"] {
+        assert_eq!(selected(&changed(&[file])), ["a", "b"], "{file}");
+    }
+    // package 自身的 manifest 只影响该 package
+    assert_eq!(selected(&changed(&["crates/a/Cargo.toml"])), ["a"]);
+    assert!(selected(&changed(&["docs/README.md"])).is_empty());
+
+    // 全局文件的改动保留所选 packages 的所有诊断
+    let data = Data {
+        cmd_idx: 0,
+        file: "src/lib.rs".into(),
+        kind: Kind::ClippyWarn,
+        raw: String::new(),
+        fingerprint: String::new(),
+        detail: DataDetail::default(),
+    };
+    assert!(changed(&["Cargo.lock"]).keep("b", &data));
+    assert!(!changed(&["crates/a/Cargo.toml"]).keep("a", &data));
+}
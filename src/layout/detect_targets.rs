@@ -15,10 +15,10 @@
 
 use super::targets::Targets;
 use crate::{
-    cli::is_not_layout,
+    cli::{is_not_layout, offline},
     utils::{
-        empty, install_toolchain, rustup_target_add, rustup_target_add_for_checkers,
-        scan_scripts_for_target, walk_dir, PECULIAR_TARGETS,
+        empty, ensure_installed, install_toolchain, rustup_target_add,
+        rustup_target_add_for_checkers, scan_scripts_for_target, walk_dir, PECULIAR_TARGETS,
     },
     Result, XString,
 };
@@ -395,8 +395,12 @@ impl RustToolchain {
     pub fn install_targets(&self) -> Result<()> {
         if let Some(targets) = self.targets.as_deref() {
             let targets: Vec<_> = targets.iter().map(|s| s.as_str()).collect();
-            let repo_dir = self.toml_path.parent().unwrap();
-            rustup_target_add(&targets, repo_dir)?;
+            if offline() {
+                ensure_installed(&self.channel, "target", &targets)?;
+            } else {
+                let repo_dir = self.toml_path.parent().unwrap();
+                rustup_target_add(&targets, repo_dir)?;
+            }
             rustup_target_add_for_checkers(&targets)?;
         }
         Ok(())
@@ -413,31 +417,38 @@ impl RustToolchain {
         if !has_clippy {
             self.need_install_clippy = true;
 
-            let repo_dir = self.toml_path.parent().unwrap();
-            let stdout = install_toolchain(repo_dir)?;
-            println!(
-                "{}\ntargets = {:#?}",
-                String::from_utf8(stdout)?,
-                self.targets
-            );
-
-            let output = cmd!(
-                "rustup",
-                "component",
-                "add",
-                "clippy",
-                "--toolchain",
-                &self.channel
-            )
-            .run()?;
-
-            ensure!(
-                output.status.success(),
-                "RustToolchain = {self:#?}\n无法给仓库设置的工具链安装 clippy：\nstderr={}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-
-            info!("仓库设置的工具链不含 clippy，os-checker 自动安装它；RustToolchain = {self:#?}");
+            if offline() {
+                // 离线时无法安装工具链和 clippy，只检查它们是否已安装
+                ensure_installed(&self.channel, "component", &["clippy"])?;
+            } else {
+                let repo_dir = self.toml_path.parent().unwrap();
+                let stdout = install_toolchain(repo_dir)?;
+                println!(
+                    "{}\ntargets = {:#?}",
+                    String::from_utf8(stdout)?,
+                    self.targets
+                );
+
+                let output = cmd!(
+                    "rustup",
+                    "component",
+                    "add",
+                    "clippy",
+                    "--toolchain",
+                    &self.channel
+                )
+                .run()?;
+
+                ensure!(
+                    output.status.success(),
+                    "RustToolchain = {self:#?}\n无法给仓库设置的工具链安装 clippy：\nstderr={}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+
+                info!(
+                    "仓库设置的工具链不含 clippy，os-checker 自动安装它；RustToolchain = {self:#?}"
+                );
+            }
 
             match self.components.as_mut() {
                 Some(v) => v.push("clippy".to_owned()),
@@ -480,6 +491,9 @@ impl RustToolchain {
     /// 虽然主机工具链很可能安装了 rustfmt，但检查一遍也是好的。
     /// 此函数用于主机工具链检查，而不是仓库工具链。
     pub fn install_rustfmt(&self) -> Result<()> {
+        if offline() {
+            return ensure_installed(&self.channel, "component", &["rustfmt"]);
+        }
         let output = cmd!("rustup", "component", "add", "rustfmt").run()?;
         ensure!(
             output.status.success(),
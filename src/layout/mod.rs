@@ -1,15 +1,16 @@
 //! 启发式了解项目的 Rust packages 组织结构。
 
 use crate::{
-    cli::no_layout_error,
-    config::{Features, Resolve, TargetEnv, TargetsSpecifed},
+    cli::{no_layout_error, offline},
+    config::{CheckerTool, Features, Flags, Resolve, TargetEnv, TargetsSpecifed},
     db::out::{CacheLayout, CachePackageInfo, CacheResolve, CargoMetaData},
     output::{get_channel, install_toolchain_idx, remove_targets, uninstall_toolchains},
     run_checker::DbRepo,
-    utils::{empty, walk_dir, Exclude},
+    utils::{empty, offline_skipped, walk_dir, Exclude},
     Result, XString,
 };
 use audit::CargoAudit;
+pub use audit::{advisory_db_dir, expected_advisory_db_commit, AuditDiagnostic, AuditDiagnostics};
 use cargo_metadata::{
     camino::{Utf8Path, Utf8PathBuf},
     Metadata, MetadataCommand, TargetKind,
@@ -31,6 +32,10 @@ pub use detect_targets::RustToolchain;
 /// run cargo audit but share the result with related pkgs
 mod audit;
 
+/// `--since` 模式：只检查和报告改动的文件
+mod changed;
+pub use changed::ChangedFiles;
+
 /// 寻找仓库内所有 Cargo.toml 所在的路径
 fn find_all_cargo_toml_paths<E: Exclude>(
     repo_root: &str,
@@ -58,7 +63,11 @@ fn parse(cargo_tomls: &[Utf8PathBuf]) -> Result<Workspaces> {
     let mut map = IndexMap::new();
     for cargo_toml in cargo_tomls {
         // NOTE: 一旦支持 features，这里可能需要传递它们
-        let metadata = match MetadataCommand::new().manifest_path(cargo_toml).exec() {
+        let mut cmd = MetadataCommand::new();
+        if offline() {
+            cmd.other_options(["--offline".to_owned()]);
+        }
+        let metadata = match cmd.manifest_path(cargo_toml).exec() {
             Ok(metadata) => metadata,
             Err(err) => {
                 if no_layout_error() {
@@ -233,7 +242,9 @@ impl Layout {
         // 中的筛选方式，而不再指向全部 package 的 Cargo.toml.
 
         let libs = lib_pkgs(&self.workspaces, &self.cargo_tomls);
-        let audit = {
+        let audit = if offline_skipped(CheckerTool::Audit) {
+            IndexMap::new()
+        } else {
             // query cargo audit with less pkgs
             let pkg_dirs: Vec<_> = if self.workspaces.len() > self.cargo_tomls.len() {
                 self.cargo_tomls
@@ -253,18 +264,13 @@ impl Layout {
             } else {
                 self.workspaces.keys().cloned().collect()
             };
-            let res = CargoAudit::new_for_pkgs(pkg_dirs);
-            match res {
-                Ok(audit) => audit,
-                Err(err) => {
-                    if no_layout_error() {
-                        error!(?err, "skip the error by setting empty audit result");
-                        Default::default()
-                    } else {
-                        return Err(err);
-                    }
-                }
-            }
+            let pkgs: Vec<_> = self
+                .packages_info
+                .iter()
+                .map(|info| (info.pkg_name.clone(), info.pkg_dir.clone()))
+                .collect();
+            // cargo-audit failures are emitted as Cargo diagnostics later
+            CargoAudit::new_for_pkgs(pkg_dirs, &pkgs)
         };
 
         let map: IndexMap<_, _> = self
@@ -288,7 +294,11 @@ impl Layout {
 
         debug!(pkgs = ?map.keys().collect::<Vec<_>>());
         let repo_root = self.repo_root().to_owned();
-        Ok(Packages { repo_root, map })
+        Ok(Packages {
+            repo_root,
+            map,
+            changed: None,
+        })
     }
 
     pub fn set_installation_targets(&mut self, targets: TargetsSpecifed) {
@@ -336,7 +346,11 @@ impl Layout {
     }
 
     /// 删除仓库工具链，但不删除主机和检查工具所在的工具链上安装的 targets。
+    /// 离线时保留工具链，因为无法重新安装它们。
     pub fn uninstall_toolchains(&self) -> Result<()> {
+        if offline() {
+            return Ok(());
+        }
         for &idx in self.installation.keys() {
             if idx != 0 {
                 uninstall_toolchains(idx)?;
@@ -420,6 +434,8 @@ pub struct Packages {
     repo_root: Utf8PathBuf,
     /// The order is by pkg_name and pkd_dir.
     map: IndexMap<XString, PackageInfoShared>,
+    /// `--since` 模式下只选择拥有改动文件的 packages
+    changed: Option<Vec<XString>>,
 }
 
 impl Packages {
@@ -444,9 +460,16 @@ impl Packages {
                     )
                 })
                 .collect(),
+            changed: None,
         }
     }
 
+    /// 只选择拥有改动文件的 packages，包括 packages 字段指定的 packages。
+    pub fn only_changed(&mut self, changed: &ChangedFiles) {
+        let names = self.map.keys().filter(|name| changed.contains_pkg(name));
+        self.changed = Some(names.cloned().collect());
+    }
+
     pub fn select<'a, I>(&self, globs: &[glob::Pattern], pkgs: I) -> Vec<(&str, &PackageInfoShared)>
     where
         I: Iterator<Item = &'a str>,
@@ -475,6 +498,10 @@ impl Packages {
             (name.as_str(), info)
         }));
 
+        if let Some(changed) = &self.changed {
+            map.retain(|name, _| changed.iter().any(|c| c == name));
+        }
+
         map.sort_unstable_keys();
         map.into_iter().collect()
     }
@@ -509,6 +536,7 @@ impl PackageInfoShared {
         name: &'a str,
         targets: Option<&'a [String]>,
         features: &[Features],
+        flags: &[&Flags],
         env: Option<&'a IndexMap<String, String>>,
         target_env: Option<&TargetEnv>,
     ) -> Result<Vec<Pkg<'a>>> {
@@ -543,6 +571,8 @@ impl PackageInfoShared {
                     .collect()
             };
 
+            let flags = Flags::args(flags.iter().copied(), target);
+
             for features_args in v_features_args {
                 pkgs.push(Pkg {
                     name,
@@ -551,6 +581,7 @@ impl PackageInfoShared {
                     features_args,
                     toolchain: self.toolchain,
                     env: env.clone(),
+                    flags: flags.clone(),
                     audit: self.audit.as_ref(),
                     is_lib: self.is_lib,
                 });
@@ -573,6 +604,8 @@ pub struct Pkg<'a> {
     pub features_args: Vec<String>,
     pub toolchain: Option<usize>,
     pub env: IndexMap<String, String>,
+    /// rustc flags and lint arguments passed to clippy
+    pub flags: Vec<String>,
     pub audit: Option<&'a Rc<CargoAudit>>,
     pub is_lib: bool,
 }
@@ -606,19 +639,16 @@ fn lib_pkgs(
                     is_lib: false,
                 },
             );
-            if no_layout_error() && old.is_some() {
+            match old {
                 // solana-foundation/anchor: Package `crank` already exists.
-                error!(
-                    "Package `{}` already exists.\nOld={:?}",
-                    p.name,
-                    old.unwrap()
-                );
-            } else {
-                assert!(
+                Some(old) if no_layout_error() => {
+                    error!("Package `{}` already exists.\nOld={old:?}", p.name);
+                }
+                _ => assert!(
                     old.is_none(),
                     "Package `{}` already exists.\nOld={old:?}",
                     p.name
-                );
+                ),
             }
             for target in &p.targets {
                 for kind in &target.kind {
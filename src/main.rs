@@ -13,6 +13,8 @@ mod cli;
 mod config;
 /// cache of checking results
 mod db;
+/// check a repo over a range of commits
+mod history;
 /// figure out the codebase layout
 mod layout;
 /// initialization of logger
@@ -0,0 +1,236 @@
+//! Stable identities of diagnostics.
+//!
+//! A fingerprint is computed from the checker, kind, lint code, normalized message,
+//! file and enclosing item, so it doesn't change with line numbers or checkout paths
+//! when unrelated code is edited. This helps diffing results across commits.
+
+use super::Kind;
+use crate::config::CheckerTool;
+use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
+use os_checker_types::Fnv1a;
+use regex::Regex;
+use std::{collections::HashMap, sync::LazyLock};
+
+#[derive(Debug)]
+pub struct Fingerprint<'a> {
+    pub checker: CheckerTool,
+    pub kind: Kind,
+    /// Lint name or advisory id if any.
+    pub code: Option<&'a str>,
+    pub message: &'a str,
+    /// File path relative to pkg dir, or absolute if outside the pkg.
+    pub file: &'a Utf8Path,
+    /// The header of the enclosing item like `fn foo(a: u8) -> u8`.
+    pub item: Option<&'a str>,
+}
+
+impl<'a> Fingerprint<'a> {
+    /// Without lint code or enclosing item.
+    pub fn new(checker: CheckerTool, kind: Kind, message: &'a str, file: &'a Utf8Path) -> Self {
+        Fingerprint {
+            checker,
+            kind,
+            code: None,
+            message,
+            file,
+            item: None,
+        }
+    }
+
+    pub fn code(self, code: Option<&'a str>) -> Self {
+        Fingerprint { code, ..self }
+    }
+
+    pub fn item(self, item: Option<&'a str>) -> Self {
+        Fingerprint { item, ..self }
+    }
+
+    /// A 16-char hex string. Checkout paths starting with `root` are removed from message;
+    /// pass an empty root if unknown.
+    pub fn hash(&self, root: &Utf8Path) -> String {
+        let Self {
+            checker,
+            kind,
+            code,
+            message,
+            file,
+            item,
+        } = self;
+        let kind = os_checker_types::Kind::from(*kind);
+        let message = normalize(message, root);
+        let item = item.map(collapse_whitespace).unwrap_or_default();
+
+        let mut hasher = Fnv1a::default();
+        for part in [
+            checker.name(),
+            kind.as_str(),
+            code.unwrap_or_default(),
+            &message,
+            file.as_str(),
+            &item,
+        ] {
+            hasher.write(part.as_bytes());
+            // separator to avoid ambiguity between adjacent parts
+            hasher.write(&[0xff]);
+        }
+        hasher.hex()
+    }
+}
+
+struct Normalization {
+    /// `src/lib.rs:10:5` => `src/lib.rs`
+    location: Regex,
+    /// `10:5` or `10:5: 12:8` left in spans
+    line_col: Regex,
+}
+
+static RE: LazyLock<Normalization> = LazyLock::new(|| Normalization {
+    location: Regex::new(r"(\.\w+):\d+(:\d+)?").unwrap(),
+    line_col: Regex::new(r"\b\d+:\d+\b").unwrap(),
+});
+
+/// Remove things that vary with unrelated edits or environment:
+/// timestamps, line/column numbers, checkout paths and whitespaces.
+fn normalize(message: &str, root: &Utf8Path) -> String {
+    let root = format!("{root}/");
+    let lines = message
+        .lines()
+        .filter(|line| !line.contains("timestamp="))
+        .map(|line| match root.as_str() {
+            "/" => line.to_owned(),
+            root => line.replace(root, ""),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let lines = RE.location.replace_all(&lines, "$1");
+    let lines = RE.line_col.replace_all(&lines, "");
+    collapse_whitespace(&lines)
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+static ITEM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*(pub(\([^)]*\))?\s+)?((const|async|unsafe|default|extern\s+\S+)\s+)*(fn|struct|enum|union|trait|impl|mod|type|static|const|macro_rules!)\b",
+    )
+    .unwrap()
+});
+
+/// Source files read when searching enclosing items, so that each file is read only once
+/// for all diagnostics in a checker output.
+#[derive(Debug, Default)]
+pub struct Sources {
+    files: HashMap<Utf8PathBuf, Option<String>>,
+}
+
+impl Sources {
+    /// Search upwards from the 1-based line for the header of the enclosing item.
+    pub fn enclosing_item(&mut self, path: &Utf8Path, line: usize) -> Option<String> {
+        let src = self
+            .files
+            .entry(path.to_owned())
+            .or_insert_with(|| std::fs::read_to_string(path).ok());
+        find_item(src.as_deref()?, line)
+    }
+}
+
+/// The line itself is returned if it's an item header. Otherwise braces are counted
+/// upwards to skip closed blocks, and the item owning the first unclosed `{` is returned.
+/// Braces in string literals are not taken into account.
+fn find_item(src: &str, line: usize) -> Option<String> {
+    let lines: Vec<_> = src.lines().take(line).collect();
+    let header = |l: &str| l.trim().trim_end_matches('{').trim_end().to_owned();
+    let (&last, above) = lines.split_last()?;
+    if ITEM.is_match(last) {
+        return Some(header(last));
+    }
+
+    let mut depth = 0usize;
+    for (idx, l) in above.iter().enumerate().rev() {
+        let code = l.split("//").next().unwrap_or_default();
+        let mut opened = false;
+        for c in code.chars().rev() {
+            match c {
+                '}' => depth += 1,
+                '{' if depth == 0 => opened = true,
+                '{' => depth -= 1,
+                _ => (),
+            }
+        }
+        if !opened {
+            continue;
+        }
+        // the header may span lines before the brace, like `fn foo(\n    a: u8,\n) {`
+        let start = above[..idx]
+            .iter()
+            .rposition(|l| l.contains(['{', '}', ';']))
+            .map_or(0, |i| i + 1);
+        if let Some(l) = above[start..=idx].iter().rev().find(|l| ITEM.is_match(l)) {
+            return Some(header(l));
+        }
+        // a block like `if` or `loop` inside an item: keep searching upwards
+    }
+    None
+}
+
+#[test]
+fn stable_fingerprint() {
+    let root = Utf8Path::new("/tmp/repos/user/repo");
+    let fingerprint = |message, item| {
+        Fingerprint::new(
+            CheckerTool::Lockbud,
+            Kind::LockbudProbably,
+            message,
+            Utf8Path::new("src/lib.rs"),
+        )
+        .item(item)
+        .hash(root)
+    };
+
+    let a = fingerprint(
+        "pkg=a\ntimestamp=2024-10-10\nDoubleLock at /tmp/repos/user/repo/src/lib.rs:10:5: 10:20",
+        Some("fn foo()"),
+    );
+    // moved lines and rerun at another time
+    let b = fingerprint(
+        "pkg=a\ntimestamp=2024-12-12\nDoubleLock at  /tmp/repos/user/repo/src/lib.rs:42:9: 43:1",
+        Some("fn   foo()"),
+    );
+    assert_eq!(a, b);
+    assert_eq!(a.len(), 16);
+
+    // a different enclosing item
+    let c = fingerprint(
+        "pkg=a\ntimestamp=2024-12-12\nDoubleLock at src/lib.rs:42:9: 43:1",
+        Some("fn bar()"),
+    );
+    assert_ne!(a, c);
+}
+
+#[test]
+fn search_enclosing_item() {
+    let src = "use std::fmt;\n\nimpl Foo {\n    pub(crate) unsafe fn foo(&self) -> u8 {\n        let a = 1;\n        a\n    }\n}\n";
+    assert_eq!(
+        find_item(src, 5).as_deref(),
+        Some("pub(crate) unsafe fn foo(&self) -> u8")
+    );
+    assert_eq!(find_item(src, 3).as_deref(), Some("impl Foo"));
+    assert_eq!(find_item(src, 1), None);
+}
+
+#[test]
+fn enclosing_item_after_closed_block() {
+    let src = "mod m {\n    fn foo() {\n        if true {\n            1;\n        }\n        2;\n    }\n    const X: u8 = 1;\n    type T = u8;\n}\nfn bar() {}\nstatic Y: u8 = 0;\nuse std::fmt;\n";
+    // inside fn foo, after a closed if block
+    assert_eq!(find_item(src, 6).as_deref(), Some("fn foo()"));
+    // after fn foo is closed, the enclosing item is mod m
+    assert_eq!(find_item(src, 9).as_deref(), Some("type T = u8;"));
+    assert_eq!(find_item(src, 10).as_deref(), Some("mod m"));
+    // top level after closed items
+    assert_eq!(find_item(src, 13), None);
+
+    let src = "pub fn foo(\n    a: u8,\n) -> u8 {\n    a\n}\n";
+    assert_eq!(find_item(src, 4).as_deref(), Some("pub fn foo("));
+}
@@ -2,10 +2,14 @@ use crate::{config::CheckerTool, run_checker::FullOrFastOutputs, XString};
 use cargo_metadata::camino::Utf8PathBuf;
 use either::Either;
 use musli::{Decode, Encode};
+use os_checker_types::DataDetail;
 use serde::Serialize;
 use std::time::SystemTime;
 
+mod fingerprint;
 mod toolchain;
+
+pub use fingerprint::{Fingerprint, Sources};
 pub use toolchain::{
     get_channel, get_toolchain, host_target_triple, host_toolchain, init_toolchain_info,
     install_toolchain_idx, push_toolchain, remove_targets, uninstall_toolchains, RustToolchains,
@@ -59,6 +63,10 @@ pub struct Env {
 pub struct Tools {
     rust_toolchains: RustToolchains,
     os_checker: ToolOsChecker,
+    checkers: Vec<ToolChecker>,
+    /// 本次运行跳过的检查工具，比如 `run --offline` 时需要网络的检查工具
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    skipped: Vec<SkippedChecker>,
 }
 
 impl Tools {
@@ -66,10 +74,49 @@ impl Tools {
         Self {
             rust_toolchains: RustToolchains::new(),
             os_checker: ToolOsChecker::new_without_duration(),
+            checkers: ToolChecker::detected(),
+            skipped: SkippedChecker::offline(),
         }
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct SkippedChecker {
+    checker: CheckerTool,
+    reason: &'static str,
+}
+
+impl SkippedChecker {
+    fn offline() -> Vec<Self> {
+        crate::utils::skipped_checkers()
+            .into_iter()
+            .map(|(checker, reason)| SkippedChecker { checker, reason })
+            .collect()
+    }
+}
+
+/// 检查工具的版本：同一个检查工具可能在不同工具链上运行
+#[derive(Debug, Serialize)]
+pub struct ToolChecker {
+    checker: CheckerTool,
+    /// Empty if the checker doesn't depend on a toolchain.
+    toolchain: String,
+    version: String,
+}
+
+impl ToolChecker {
+    fn detected() -> Vec<Self> {
+        crate::utils::checker_versions()
+            .into_iter()
+            .map(|(checker, toolchain, version)| ToolChecker {
+                checker,
+                toolchain,
+                version,
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ToolOsChecker {
     start: u64,
@@ -124,12 +171,6 @@ pub struct PackageRepo {
     pub repo: XString,
 }
 
-#[derive(Debug, Serialize)]
-pub struct PackageCargo {
-    pub targets: Vec<XString>,
-    pub features: Vec<XString>,
-}
-
 #[derive(Debug, Serialize)]
 pub struct Cmd {
     pub package_idx: usize,
@@ -156,11 +197,15 @@ pub struct Data {
     pub file: Utf8PathBuf,
     pub kind: Kind,
     pub raw: String,
+    /// Stable identity of the diagnostic across commits: unaffected by line numbers,
+    /// timestamps and checkout paths.
+    pub fingerprint: String,
+    #[serde(flatten)]
+    pub detail: DataDetail,
 }
 
 /// The kind a checker reports.
 #[derive(Debug, Serialize, Decode, Encode, Clone, Copy)]
-#[allow(dead_code)]
 pub enum Kind {
     /// fmt
     Unformatted,
@@ -191,6 +236,17 @@ pub enum Kind {
     Cargo,
 }
 
+impl Kind {
+    /// 针对整个 crate 而不是具体源码位置的诊断，比如依赖漏洞和编译失败。
+    pub fn is_crate_wide(self) -> bool {
+        use Kind::*;
+        matches!(
+            self,
+            SemverViolation | Audit | Outdated | Geiger | Udeps | Cargo
+        )
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct Kinds {
     order: Vec<Kind>,
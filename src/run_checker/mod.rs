@@ -1,9 +1,10 @@
 use crate::{
+    cli::{patch_dir, since, since_keep_crate_wide},
     config::{CheckerTool, Config, Resolve},
-    db::{CacheRepo, InfoKeyValue},
-    layout::{Audit, Layout},
+    db::{CacheRepo, CacheValue, InfoKeyValue},
+    layout::{AuditDiagnostics, ChangedFiles, Layout},
     output::JsonOutput,
-    utils::{env_var::force_repo_check, Exclude},
+    utils::{env_var::force_repo_check, offline_skipped, skipped_checkers, Exclude},
     Result, XString,
 };
 use cargo_metadata::{
@@ -24,9 +25,14 @@ use time::OffsetDateTime;
 mod geiger;
 mod lockbud;
 mod outdated;
+mod patch;
 mod rap;
 mod rudra;
 mod semver_checks;
+mod udeps;
+
+/// 每个检查工具与工具链组合独立的 CARGO_TARGET_DIR
+mod target_dir;
 
 /// 把获得的输出转化成 JSON 所需的输出
 mod utils;
@@ -35,6 +41,8 @@ pub use utils::DbRepo;
 mod packages_outputs;
 use packages_outputs::PackagesOutputs;
 
+use patch::Patches;
+
 pub struct RepoOutput {
     repo: Repo,
     outputs: PackagesOutputs,
@@ -55,15 +63,33 @@ pub struct FastOutputs {
 }
 
 impl FastOutputs {
+    fn new(config: Config, caches: Vec<(&str, CacheValue)>) -> Self {
+        // 离线时被跳过的检查不输出缓存的结果，以便与 env.tools.skipped 一致
+        let skipped = skipped_checkers();
+        let caches: Vec<_> = caches
+            .into_iter()
+            .filter(|(_, cache)| !skipped.iter().any(|(c, _)| *c == cache.checker()))
+            .collect();
+        FastOutputs {
+            config,
+            outputs: caches.into(),
+        }
+    }
+
     pub fn with_json_output(&self, json: &mut JsonOutput) {
-        with_json_output(&self.config, &self.outputs, json);
+        with_json_output(&self.config, &self.outputs, None, json);
     }
 }
 
-pub fn with_json_output(config: &Config, outputs: &PackagesOutputs, json: &mut JsonOutput) {
+pub fn with_json_output(
+    config: &Config,
+    outputs: &PackagesOutputs,
+    changed: Option<&ChangedFiles>,
+    json: &mut JsonOutput,
+) {
     use crate::output::*;
     let user = XString::new(config.user_name());
-    let repo = XString::new(config.repo_name());
+    let repo = config.output_repo_name();
     let repo_idx = json.env.repos.len();
 
     // 预留足够的空间
@@ -82,7 +108,15 @@ pub fn with_json_output(config: &Config, outputs: &PackagesOutputs, json: &mut J
             // rust_toolchain_idx: v.as_slice().first().and_then(|o| o.resolve.toolchain),
         });
         for o in v.as_slice() {
+            let start = json.data.len();
             utils::push_idx_and_data(pkg_idx, o, &mut json.cmd, &mut json.data);
+            if let Some(changed) = changed {
+                // 只保留改动文件中的诊断
+                let data = json.data.split_off(start);
+                json.data
+                    .extend(data.into_iter().filter(|d| changed.keep(pkg_name, d)));
+                json.cmd.last_mut().unwrap().count = json.data.len() - start;
+            }
         }
     }
 
@@ -96,7 +130,8 @@ pub fn with_json_output(config: &Config, outputs: &PackagesOutputs, json: &mut J
 
 impl RepoOutput {
     pub fn with_json_output(&self, json: &mut JsonOutput) {
-        with_json_output(&self.repo.config, &self.outputs, json);
+        let changed = self.repo.changed.as_ref();
+        with_json_output(&self.repo.config, &self.outputs, changed, json);
     }
 
     /// 提前删除仓库目录
@@ -109,6 +144,8 @@ impl RepoOutput {
 pub struct Repo {
     layout: Layout,
     config: Config,
+    /// `--since` 模式下的改动文件
+    changed: Option<ChangedFiles>,
 }
 
 impl Repo {
@@ -121,12 +158,17 @@ impl Repo {
         let layout = Layout::parse(repo_root, dirs_excluded, only_dirs)
             .with_context(|| eyre!("无法解析 `{repo_root}` 内的 Rust 项目布局"));
         match layout {
-            Ok(layout) => Self { layout, config },
+            Ok(layout) => Self {
+                layout,
+                config,
+                changed: None,
+            },
             Err(err) => {
                 error!(?err);
                 Self {
                     layout: Layout::empty(repo_root, err),
                     config,
+                    changed: None,
                 }
             }
         }
@@ -135,7 +177,21 @@ impl Repo {
     fn resolve(&self) -> Result<Either<Vec<Resolve>, &str>> {
         match self.layout.get_parse_error() {
             Some(err) => Ok(Either::Right(err)),
-            None => Ok(Either::Left(self.config.resolve(&self.layout.packages()?)?)),
+            None => {
+                let mut pkgs = self.layout.packages()?;
+                if let Some(changed) = &self.changed {
+                    pkgs.only_changed(changed);
+                }
+                let mut resolves = self.config.resolve(&pkgs)?;
+                resolves.retain(|r| {
+                    let skipped = offline_skipped(r.checker);
+                    if skipped {
+                        warn!(checker = ?r.checker, pkg = %r.pkg_name, "skipped when offline");
+                    }
+                    !skipped
+                });
+                Ok(Either::Left(resolves))
+            }
         }
     }
 
@@ -152,7 +208,7 @@ impl Repo {
         let db = self.config.db();
         let repo = {
             let root = self.layout.repo_root();
-            CacheRepo::new(user, repo, root)?
+            CacheRepo::new(user, repo, root, self.config.rev())?
         };
         info.assert_eq_sha(&repo);
         let db_repo = db.map(|db| DbRepo::new(db, &repo, info));
@@ -162,10 +218,17 @@ impl Repo {
             return Ok(outputs);
         }
 
+        let patch_dir = patch_dir();
+        let mut patches = patch_dir
+            .is_some()
+            .then(|| Patches::new(self.layout.repo_root()));
+
         let err_or_resolve = self.resolve()?;
         match err_or_resolve {
             Either::Left(mut resolves) => {
                 self.layout.set_layout_cache(&resolves, db_repo);
+                // 统计 target dirs 的大小需要遍历所有编译产物，因此每个仓库只清理一次
+                target_dir::prune();
 
                 resolves.sort_by_key(|r| r.checker);
                 let len_resolves = resolves.len();
@@ -182,7 +245,6 @@ impl Repo {
                 for (idx_checker, (checker, checker_resolves)) in
                     group_by_checker.into_iter().enumerate()
                 {
-                    checker.cargo_clean(&self.layout.workspace_dirs());
                     let len_checker_resolves = checker_resolves.len();
                     for (idx_resolve, resolve) in checker_resolves.into_iter().enumerate() {
                         debug!(
@@ -194,7 +256,7 @@ impl Repo {
                             len_checker_resolves = %(len_checker_resolves.blue().bold()),
                             idx_resolve = %(idx_resolve.blue())
                         );
-                        run_check(resolve, &mut outputs, db_repo)?;
+                        run_check(resolve, &mut outputs, db_repo, patches.as_mut())?;
                         len_finished_resolves += 1;
                     }
                 }
@@ -203,6 +265,12 @@ impl Repo {
                 self.push_cargo_layout_parse_error(err, &mut outputs, db_repo);
             }
         }
+
+        if let (Some(dir), Some(patches)) = (&patch_dir, &patches) {
+            if let Err(err) = patches.write(dir, user, &self.config.output_repo_name()) {
+                error!(?err, "failed to write patches");
+            }
+        }
         Ok(outputs)
     }
 
@@ -237,12 +305,13 @@ impl TryFrom<Config> for Repo {
         let repo_root = config.local_root_path_with_git_clone()?;
         let skip_dir = config.skip_pkg_dir_globs();
         let only_dir = config.only_pkg_dir_globs();
-        Ok(Repo::new_or_empty(
-            repo_root.as_str(),
-            skip_dir,
-            &only_dir,
-            config,
-        ))
+        let mut repo = Repo::new_or_empty(repo_root.as_str(), skip_dir, &only_dir, config);
+        // 布局解析出错时报告完整的错误，无需筛选
+        if let (Some(since), None) = (since(), repo.layout.get_parse_error()) {
+            let keep = since_keep_crate_wide();
+            repo.changed = Some(ChangedFiles::new(&repo.layout, &since, keep)?);
+        }
+        Ok(repo)
     }
 }
 
@@ -253,16 +322,23 @@ impl RepoOutput {
         let _span =
             error_span!("run", user = config.user_name(), repo = config.repo_name()).entered();
 
+        // --since 只检查部分 packages，并且需要仓库目录来筛选诊断，因此不使用整个仓库的缓存；
+        // 但每个检查命令的结果仍然会被缓存
+        let partial = since().is_some();
+        // --patch-dir 需要原始的检查输出来生成补丁，因此同样不使用整个仓库的缓存
+        let skip_repo_cache = partial || patch_dir().is_some();
+
         let info = match config.new_info()? {
             Either::Left(info) => info,
             Either::Right(cached_info) => {
-                if let Some(db) = config.db() {
-                    match cached_info.info_value().get_cache_values(db) {
+                let info_value = cached_info.info_value();
+                if let Some(db) = config
+                    .db()
+                    .filter(|_| !skip_repo_cache && info_value.is_up_to_date())
+                {
+                    match info_value.get_cache_values(db) {
                         Ok(caches) => {
-                            return Ok(Either::Right(FastOutputs {
-                                config,
-                                outputs: caches.into(),
-                            }));
+                            return Ok(Either::Right(FastOutputs::new(config, caches)));
                         }
                         Err(err) => {
                             error!(?err, "Failed to get values from cached_info.")
@@ -275,25 +351,28 @@ impl RepoOutput {
 
         if force_repo_check() || config.rerun() {
             warn!("强制运行检查（不影响已有的检查缓存结果）");
+        } else if partial {
+            info!("--since 模式：只检查改动的 packages");
+        } else if skip_repo_cache {
+            info!("--patch-dir 模式：重新运行可生成补丁的检查");
         } else if let Some(db) = config.db() {
             match info.get_from_db(db) {
                 Ok(Some(info_cache)) => {
-                    if info_cache.is_complete() {
+                    if !info_cache.is_complete() {
+                        warn!("仓库检查结果缓存不完整");
+                    } else if !info_cache.is_up_to_date() {
+                        warn!("检查工具或 advisory-db 已更新，仓库检查结果缓存已过时");
+                    } else {
                         info!("成功获取完整的仓库检查结果键缓存");
                         match info_cache.get_cache_values(db) {
                             Ok(caches) => {
                                 // push check item if caching is found
                                 info.check_push_info_key(db)?;
 
-                                return Ok(Either::Right(FastOutputs {
-                                    config,
-                                    outputs: caches.into(),
-                                }));
+                                return Ok(Either::Right(FastOutputs::new(config, caches)));
                             }
                             Err(err) => error!(?err, "存在不正确的检查结果键或值数据"),
                         }
-                    } else {
-                        warn!("仓库检查结果缓存不完整");
                     }
                 }
                 Ok(None) => warn!("该仓库无所有检查结果的键缓存"),
@@ -321,7 +400,9 @@ impl RepoOutput {
 
         let mut outputs = repo.run_check(&info, install_err)?;
         outputs.sort_by_name_and_checkers();
-        if let Some(db) = repo.config.db() {
+        // 离线时跳过了部分检查，与 --since 一样，不把仓库的缓存标记为完整
+        let incomplete = partial || !skipped_checkers().is_empty();
+        if let Some(db) = repo.config.db().filter(|_| !incomplete) {
             info.set_complete(db)?;
             // push check item if caching is done
             info.check_push_info_key(db)?;
@@ -443,16 +524,21 @@ fn run_check(
     resolve: Resolve,
     outputs: &mut PackagesOutputs,
     db_repo: Option<DbRepo>,
+    patches: Option<&mut Patches>,
 ) -> Result<()> {
     // 从缓存中获取结果，如果获取成功，则不执行实际的检查
     // FIXME: 当 force_check 后如果 Cargo 不再有诊断，那么下次读取缓存的话，那么会看到旧的 Cargo 诊断？
-    if !resolve.force_check() && outputs.fetch_cache(&resolve, db_repo) {
+    // 补丁由原始输出生成，因此在导出补丁时，可生成补丁的检查不读取缓存
+    let patching = patches.is_some() && Patches::supports(resolve.checker);
+    if !resolve.force_check() && !patching && outputs.fetch_cache(&resolve, db_repo) {
         // if !utils::force_run_check() && outputs.fetch_cache(&resolve, db_repo) {
         return Ok(());
     }
 
+    target_dir::clean(&resolve);
     let (now_utc, duration_ms, raw) = crate::utils::execution_time_ms(|| {
-        let expr = resolve.expr.stderr_capture().stdout_capture().unchecked();
+        let expr = target_dir::expr(&resolve);
+        let expr = expr.stderr_capture().stdout_capture().unchecked();
         expr.run()
     });
     let raw = raw?;
@@ -515,21 +601,36 @@ fn run_check(
         ))),
         CheckerTool::Rapx => Ok(OutputParsed::Rap(rap::rap_output(stderr, stdout, &resolve))),
         CheckerTool::Rudra => Ok(OutputParsed::Rudra(rudra::parse(stderr, &resolve))),
-        CheckerTool::Audit => Ok(OutputParsed::Audit(resolve.audit.clone())),
-        CheckerTool::Outdated => Ok(OutputParsed::Outdated(outdated::parse_outdated(
-            &raw, &resolve,
-        ))),
+        CheckerTool::Audit => resolve
+            .audit
+            .as_deref()
+            .map(|audit| audit.diagnostics(&resolve.pkg_name))
+            .transpose()
+            .map(OutputParsed::Audit)
+            .map_err(|err| (CheckerTool::Audit, err)),
+        CheckerTool::Outdated => outdated::parse_outdated(&raw, &resolve)
+            .map(OutputParsed::Outdated)
+            .map_err(|err| (CheckerTool::Outdated, err)),
         CheckerTool::Geiger => Ok(OutputParsed::Geiger(geiger::parse(&raw, &resolve))),
         CheckerTool::Miri => todo!(),
         CheckerTool::SemverChecks => Ok(OutputParsed::SemverChecks(semver_checks::parse(
             &raw, &resolve,
         ))),
-        // reuse semver_checks::parse for udeps, since they are likewise.
-        CheckerTool::Udeps => Ok(OutputParsed::Udeps(semver_checks::parse(&raw, &resolve))),
+        CheckerTool::Udeps => udeps::parse(&raw, &resolve)
+            .map(OutputParsed::Udeps)
+            .map_err(|err| (CheckerTool::Udeps, err)),
         // 由于 run_check 只输出单个 Ouput，而其他检查工具可能会利用 cargo，因此导致发出两类诊断
         CheckerTool::Cargo => panic!("Don't specify cargo as a checker. It's a virtual one."),
     };
 
+    match (patches, &parsed) {
+        (Some(patches), Ok(OutputParsed::Clippy(v))) => {
+            patches.push_clippy(&resolve.pkg_name, &resolve.pkg_dir, v)
+        }
+        (Some(patches), Ok(OutputParsed::Fmt(v))) => patches.push_fmt(&resolve.pkg_dir, v),
+        _ => (),
+    }
+
     let output = match parsed {
         Ok(parsed) => Output {
             raw,
@@ -550,17 +651,17 @@ fn run_check(
 enum OutputParsed {
     Fmt(Box<[FmtMessage]>),
     Clippy(Box<[RustcMessage]>),
-    Audit(Audit),
+    Audit(Option<AuditDiagnostics>),
     Mirai(Box<[RustcMessage]>),
     // TODO: a good type for Lockbud and Rap output is Option<String>
     Lockbud(String),
     Atomvchecker(String),
     Rap(String),
     Rudra(String),
-    Outdated(String),
+    Outdated(Box<[outdated::OutdatedDep]>),
     Geiger(String),
     SemverChecks(String),
-    Udeps(String),
+    Udeps(Box<[udeps::UnusedDep]>),
     Cargo { source: CargoSource, stderr: String },
 }
 
@@ -601,21 +702,19 @@ impl OutputParsed {
             | OutputParsed::Atomvchecker(s)
             | OutputParsed::Rap(s)
             | OutputParsed::Rudra(s)
-            | OutputParsed::Outdated(s)
             | OutputParsed::Geiger(s)
-            | OutputParsed::SemverChecks(s)
-            | OutputParsed::Udeps(s) => {
+            | OutputParsed::SemverChecks(s) => {
                 if s.is_empty() {
                     0
                 } else {
                     1
                 }
             }
-            // 这个计数也是粗糙的
-            OutputParsed::Audit(audit) => audit
-                .as_deref()
-                .map(|a| a.is_problematic() as usize)
-                .unwrap_or(0),
+            // 每个过时或未使用的依赖项计为一个问题
+            OutputParsed::Outdated(v) => v.len(),
+            OutputParsed::Udeps(v) => v.len(),
+            // 每个安全公告或警告计为一个问题
+            OutputParsed::Audit(audit) => audit.as_ref().map_or(0, |a| a.findings.len()),
             // NOTE: 这个计数不准确，但也不能调用 Vec:::len，因为它包含的 Vec 是动态的，
             // 而最终输出到 JSON 的计数并不调用此方法，因此这里简单的设置为 0，
             // 虽然从最终计数看，cargo 的诊断数量应为 Vec::len。
@@ -1,12 +1,97 @@
-pub fn parse_outdated(out: &std::process::Output, resolve: &crate::config::Resolve) -> String {
-    // handle exit code 2 which is defined in Resolve cmd
-    if out.status.code() == Some(2) {
-        // already no color
-        return format!(
-            "{}{}",
-            resolve.display(),
-            String::from_utf8_lossy(&out.stdout)
-        );
+use crate::{config::Resolve, Result};
+use eyre::Context;
+use serde::Deserialize;
+use std::fmt;
+
+/// One line of `cargo outdated --format json`: each crate in the workspace
+/// reports its own outdated dependencies.
+#[derive(Debug, Deserialize)]
+struct OutdatedCrate {
+    dependencies: Vec<OutdatedDep>,
+}
+
+/// An outdated direct dependency.
+#[derive(Debug, Deserialize)]
+pub struct OutdatedDep {
+    pub name: String,
+    /// The version currently used by the project.
+    pub project: String,
+    /// The latest semver-compatible version, or `---` if none.
+    pub compat: String,
+    /// The latest version, or `---` if none.
+    pub latest: String,
+    /// Normal, Development or Build.
+    pub kind: Option<String>,
+    pub platform: Option<String>,
+}
+
+impl fmt::Display for OutdatedDep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            name,
+            project,
+            compat,
+            latest,
+            kind,
+            platform,
+        } = self;
+        let kind = kind.as_deref().unwrap_or("Normal");
+        write!(
+            f,
+            "outdated {kind} dependency `{name}`: project={project}, \
+             compat={compat}, latest={latest}"
+        )?;
+        if let Some(platform) = platform {
+            write!(f, ", platform={platform}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_outdated(out: &std::process::Output, resolve: &Resolve) -> Result<Box<[OutdatedDep]>> {
+    // exit code 2 is defined in Resolve cmd to indicate outdated dependencies
+    let code = out.status.code();
+    eyre::ensure!(
+        matches!(code, Some(0 | 2)),
+        "cargo-outdated 执行失败（exit code = {code:?}）\n原始命令为：`{}`\nstderr={}",
+        resolve.cmd,
+        String::from_utf8_lossy(&out.stderr)
+    );
+    parse_outdated_stdout(&out.stdout).with_context(|| {
+        format!(
+            "无法解析 cargo-outdated 的标准输出：stdout={}\n原始命令为：`{}`\nstderr={}",
+            String::from_utf8_lossy(&out.stdout),
+            resolve.cmd,
+            String::from_utf8_lossy(&out.stderr)
+        )
+    })
+}
+
+fn parse_outdated_stdout(stdout: &[u8]) -> Result<Box<[OutdatedDep]>> {
+    let mut deps = Vec::new();
+    for line in stdout.split(|b| *b == b'\n') {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let krate: OutdatedCrate = serde_json::from_slice(line)?;
+        deps.extend(krate.dependencies);
     }
-    String::new()
+    Ok(deps.into())
+}
+
+#[test]
+fn parse_outdated_json() -> Result<()> {
+    let stdout = br#"{"crate_name":"os-checker","dependencies":[{"name":"indexmap","project":"1.9.3","compat":"---","latest":"2.7.0","kind":"Normal","platform":null},{"name":"cc","project":"1.0.0","compat":"1.2.5","latest":"1.2.5","kind":"Build","platform":"cfg(unix)"}]}
+{"crate_name":"os-checker-types","dependencies":[]}
+"#;
+    let deps = parse_outdated_stdout(stdout)?;
+    let raw: Vec<_> = deps.iter().map(|d| d.to_string()).collect();
+    expect_test::expect![[r#"
+        [
+            "outdated Normal dependency `indexmap`: project=1.9.3, compat=---, latest=2.7.0",
+            "outdated Build dependency `cc`: project=1.0.0, compat=1.2.5, latest=1.2.5, platform=cfg(unix)",
+        ]
+    "#]]
+    .assert_debug_eq(&raw);
+    Ok(())
 }
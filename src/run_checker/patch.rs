@@ -0,0 +1,473 @@
+//! Export auto-fixable checking results as unified diffs, so that maintainers
+//! of checked repos can `git apply` them in the repo root.
+//!
+//! Patches are written to `<patch_dir>/<user>/<repo>/<pkg>/<checker>.patch`,
+//! except for rustfmt whose patch is per repo, i.e. `<patch_dir>/<user>/<repo>/fmt.patch`.
+//! Since they are generated from raw checker outputs, the cache is bypassed for these
+//! checkers when patches are requested.
+
+use super::{CargoMessage, FmtMessage, FmtMismatch, RustcMessage};
+use crate::{config::CheckerTool, Result, XString};
+use cargo_metadata::{
+    camino::{Utf8Path, Utf8PathBuf},
+    diagnostic::{Applicability, Diagnostic, DiagnosticSpan},
+};
+use indexmap::IndexMap;
+use prettydiff::basic::DiffOp;
+use std::fmt::Write;
+
+/// Replace bytes in `start..end` of a file with `text`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// Edits of one suggestion, which are applied all or nothing.
+type EditGroup = Vec<Edit>;
+
+/// Key is the file path relative to repo root.
+type FileEdits = IndexMap<Utf8PathBuf, Vec<EditGroup>>;
+
+#[derive(Debug)]
+pub struct Patches {
+    root: Utf8PathBuf,
+    /// Edits from all targets of the same pkg and checker are merged.
+    map: IndexMap<XString, IndexMap<CheckerTool, FileEdits>>,
+    /// Edits shared by the whole repo, i.e. rustfmt.
+    repo: IndexMap<CheckerTool, FileEdits>,
+}
+
+impl Patches {
+    pub fn new(root: &Utf8Path) -> Self {
+        Patches {
+            root: root.to_owned(),
+            map: IndexMap::new(),
+            repo: IndexMap::new(),
+        }
+    }
+
+    /// Checkers whose outputs can be turned into patches.
+    pub fn supports(checker: CheckerTool) -> bool {
+        matches!(checker, CheckerTool::Clippy | CheckerTool::Fmt)
+    }
+
+    fn file_edits(&mut self, pkg_name: &str, checker: CheckerTool) -> &mut FileEdits {
+        self.map
+            .entry(pkg_name.into())
+            .or_default()
+            .entry(checker)
+            .or_default()
+    }
+
+    /// Collect `MachineApplicable` suggestions from clippy diagnostics.
+    pub fn push_clippy(&mut self, pkg_name: &str, pkg_dir: &Utf8Path, v: &[RustcMessage]) {
+        /// Spans of a diagnostic make up one suggestion.
+        fn suggestions<'a>(diag: &'a Diagnostic, buf: &mut Vec<Vec<&'a DiagnosticSpan>>) {
+            let spans: Vec<_> = diag
+                .spans
+                .iter()
+                .filter(|span| {
+                    span.suggested_replacement.is_some()
+                        && span.suggestion_applicability == Some(Applicability::MachineApplicable)
+                })
+                .collect();
+            if !spans.is_empty() {
+                buf.push(spans);
+            }
+            for child in &diag.children {
+                suggestions(child, buf);
+            }
+        }
+
+        let mut groups = Vec::new();
+        for mes in v {
+            if let CargoMessage::CompilerMessage(cmes) = &mes.inner {
+                suggestions(&cmes.message, &mut groups);
+            }
+        }
+        if groups.is_empty() {
+            return;
+        }
+
+        let root = self.root.clone();
+        let file_edits = self.file_edits(pkg_name, CheckerTool::Clippy);
+        'group: for spans in groups {
+            let mut edits = IndexMap::<Utf8PathBuf, EditGroup>::new();
+            for span in spans {
+                // suggestions touching files outside the repo like dependencies are skipped
+                let Some(file) = resolve_file(&span.file_name, pkg_dir, &root) else {
+                    continue 'group;
+                };
+                edits.entry(file).or_default().push(Edit {
+                    start: span.byte_start as usize,
+                    end: span.byte_end as usize,
+                    text: span.suggested_replacement.clone().unwrap_or_default(),
+                });
+            }
+            for (file, group) in edits {
+                file_edits.entry(file).or_default().push(group);
+            }
+        }
+    }
+
+    /// Collect unformatted hunks from rustfmt into the repo patch.
+    pub fn push_fmt(&mut self, pkg_dir: &Utf8Path, v: &[FmtMessage]) {
+        let root = self.root.clone();
+        for mes in v {
+            let Some(file) = resolve_file(mes.name.as_str(), pkg_dir, &root) else {
+                continue;
+            };
+            let src = match std::fs::read_to_string(root.join(&file)) {
+                Ok(src) => src,
+                Err(err) => {
+                    error!(%file, ?err, "unable to read the unformatted file");
+                    continue;
+                }
+            };
+            let line_starts = line_starts(&src);
+            let file_edits = self.repo.entry(CheckerTool::Fmt).or_default();
+            let edits = file_edits.entry(file).or_default();
+            for mis in &mes.mismatches {
+                match fmt_edit(&src, &line_starts, mis) {
+                    Some(edit) => edits.push(vec![edit]),
+                    None => warn!(
+                        name = %mes.name,
+                        mis.original_begin_line,
+                        mis.original_end_line,
+                        "the unformatted lines don't match the file"
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Write patches into `<dir>/<user>/<repo>/<pkg>/<checker>.patch`
+    /// and `<dir>/<user>/<repo>/<checker>.patch`.
+    pub fn write(&self, dir: &Utf8Path, user: &str, repo: &str) -> Result<()> {
+        for (checker, file_edits) in &self.repo {
+            let patch = self.diff(file_edits)?;
+            if patch.is_empty() {
+                continue;
+            }
+            let repo_dir = Utf8PathBuf::from_iter([dir.as_str(), user, repo]);
+            std::fs::create_dir_all(&repo_dir)?;
+            let path = repo_dir.join(format!("{}.patch", checker.name()));
+            std::fs::write(&path, patch)?;
+            info!(%path, "patch is written");
+        }
+        for (pkg_name, checkers) in &self.map {
+            for (checker, file_edits) in checkers {
+                let patch = self.diff(file_edits)?;
+                if patch.is_empty() {
+                    continue;
+                }
+                let pkg_dir = Utf8PathBuf::from_iter([dir.as_str(), user, repo, pkg_name]);
+                std::fs::create_dir_all(&pkg_dir)?;
+                let path = pkg_dir.join(format!("{}.patch", checker.name()));
+                std::fs::write(&path, patch)?;
+                info!(%path, "patch is written");
+            }
+        }
+        Ok(())
+    }
+
+    fn diff(&self, file_edits: &FileEdits) -> Result<String> {
+        let mut files: Vec<_> = file_edits.iter().collect();
+        files.sort_unstable_by_key(|(file, _)| *file);
+
+        let mut patch = String::new();
+        for (file, edits) in files {
+            let path = self.root.join(file);
+            let old = std::fs::read_to_string(&path)?;
+            let new = apply_edits(&old, edits);
+            patch.push_str(&unified_diff(file.as_str(), &old, &new));
+        }
+        Ok(patch)
+    }
+}
+
+/// Paths in rustc diagnostics are relative to workspace root which is an ancestor
+/// of pkg dir, or absolute. Returns the path relative to repo root.
+fn resolve_file(file_name: &str, pkg_dir: &Utf8Path, root: &Utf8Path) -> Option<Utf8PathBuf> {
+    let path = find_file(file_name, pkg_dir)?;
+    path.strip_prefix(root).ok().map(Utf8Path::to_owned)
+}
+
+/// rustc reports paths relative to the workspace root, which may be any ancestor
+/// of pkg dir; this returns the full path of an existing file.
+pub fn find_file(file_name: &str, pkg_dir: &Utf8Path) -> Option<Utf8PathBuf> {
+    let file = Utf8Path::new(file_name);
+    if file.is_absolute() {
+        Some(file.to_owned())
+    } else {
+        pkg_dir
+            .ancestors()
+            .map(|dir| dir.join(file))
+            .find(|path| path.is_file())
+    }
+}
+
+/// Byte offsets of the start of each line, plus the file length.
+fn line_starts(src: &str) -> Vec<usize> {
+    let mut v: Vec<_> = std::iter::once(0)
+        .chain(src.match_indices('\n').map(|(idx, _)| idx + 1))
+        .filter(|idx| *idx < src.len())
+        .collect();
+    v.push(src.len());
+    v
+}
+
+/// Convert a rustfmt mismatch to an edit with the 1-based inclusive original line range.
+/// An empty original means insertion before `original_begin_line`.
+fn fmt_edit(src: &str, line_starts: &[usize], mis: &FmtMismatch) -> Option<Edit> {
+    let begin = (mis.original_begin_line as usize).checked_sub(1)?;
+    let start = *line_starts.get(begin)?;
+    let end = if mis.original.is_empty() {
+        start
+    } else {
+        *line_starts.get(mis.original_end_line as usize)?
+    };
+    let original = src.get(start..end)?;
+    if original.strip_suffix('\n').unwrap_or(original) != &*mis.original {
+        return None;
+    }
+    let text = if mis.expected.is_empty() {
+        String::new()
+    } else if original.is_empty() || original.ends_with('\n') {
+        format!("{}\n", mis.expected)
+    } else {
+        // the last line without trailing newline
+        mis.expected.to_string()
+    };
+    Some(Edit { start, end, text })
+}
+
+/// A group of edits is skipped as a whole if any edit in it is invalid or overlaps
+/// with an edit already taken, so that a suggestion is never half-applied.
+fn apply_edits(src: &str, groups: &[EditGroup]) -> String {
+    let mut groups: Vec<EditGroup> = groups
+        .iter()
+        .map(|group| {
+            let mut group = group.clone();
+            group.sort_unstable();
+            group.dedup();
+            group
+        })
+        .collect();
+    // duplicated suggestions come from other targets
+    groups.sort_unstable();
+    groups.dedup();
+
+    let overlap = |a: &Edit, b: &Edit| a.start < b.end && b.start < a.end;
+    let mut taken = Vec::<Edit>::new();
+    for group in groups {
+        let valid = group.iter().enumerate().all(|(idx, edit)| {
+            edit.start <= edit.end
+                && edit.end <= src.len()
+                && src.is_char_boundary(edit.start)
+                && src.is_char_boundary(edit.end)
+                && !group[..idx].iter().any(|e| overlap(e, edit))
+                && !taken.iter().any(|e| overlap(e, edit))
+        });
+        if valid {
+            taken.extend(group);
+        }
+    }
+    taken.sort_unstable();
+
+    let mut buf = String::with_capacity(src.len());
+    let mut pos = 0;
+    for Edit { start, end, text } in &taken {
+        buf.push_str(&src[pos..*start]);
+        buf.push_str(text);
+        pos = *end;
+    }
+    buf.push_str(&src[pos..]);
+    buf
+}
+
+/// Number of unchanged lines around changes in a hunk.
+const CONTEXT: usize = 3;
+
+/// Generate a unified diff for a file. Empty string means no change.
+fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<_> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<_> = new.split_inclusive('\n').collect();
+
+    let mut lines = Vec::<(char, &str)>::with_capacity(old_lines.len().max(new_lines.len()));
+    for op in prettydiff::basic::diff(&old_lines, &new_lines) {
+        match op {
+            DiffOp::Equal(a) => lines.extend(a.iter().map(|l| (' ', *l))),
+            DiffOp::Remove(a) => lines.extend(a.iter().map(|l| ('-', *l))),
+            DiffOp::Insert(b) => lines.extend(b.iter().map(|l| ('+', *l))),
+            DiffOp::Replace(a, b) => {
+                lines.extend(a.iter().map(|l| ('-', *l)));
+                lines.extend(b.iter().map(|l| ('+', *l)));
+            }
+        }
+    }
+
+    // merge changes within 2*CONTEXT lines into a hunk
+    let mut hunks = Vec::<(usize, usize)>::new();
+    for (idx, _) in lines.iter().enumerate().filter(|(_, (tag, _))| *tag != ' ') {
+        let start = idx.saturating_sub(CONTEXT);
+        let end = (idx + 1 + CONTEXT).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    // line numbers before each line in old and new files
+    let (mut old_pos, mut new_pos) = (
+        Vec::with_capacity(lines.len()),
+        Vec::with_capacity(lines.len()),
+    );
+    let (mut o, mut n) = (0, 0);
+    for (tag, _) in &lines {
+        old_pos.push(o);
+        new_pos.push(n);
+        match tag {
+            ' ' => (o, n) = (o + 1, n + 1),
+            '-' => o += 1,
+            _ => n += 1,
+        }
+    }
+
+    let mut buf = String::with_capacity(old.len() / 4);
+    _ = writeln!(
+        buf,
+        "diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}"
+    );
+    for (start, end) in hunks {
+        let hunk = &lines[start..end];
+        let old_len = hunk.iter().filter(|(tag, _)| *tag != '+').count();
+        let new_len = hunk.iter().filter(|(tag, _)| *tag != '-').count();
+        // an empty range starts at the line before it
+        let old_start = old_pos[start] + (old_len != 0) as usize;
+        let new_start = new_pos[start] + (new_len != 0) as usize;
+        _ = writeln!(buf, "@@ -{old_start},{old_len} +{new_start},{new_len} @@");
+        for (tag, line) in hunk {
+            buf.push(*tag);
+            buf.push_str(line);
+            if !line.ends_with('\n') {
+                buf.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    buf
+}
+
+#[test]
+fn edits_to_unified_diff() {
+    let old = "fn main() {\n    let a = 1;\n    let b = 2;\n    let c = 3;\n    let d = 4;\n    \
+               let e = 5;\n    let f = 6;\n    let g = 7;\n    let h = 8;\n    println!(\"{}\", a.clone());\n}";
+    let edits = [
+        vec![Edit {
+            start: 16,
+            end: 21,
+            text: "x".into(),
+        }],
+        // duplicated edit from another target
+        vec![Edit {
+            start: 16,
+            end: 21,
+            text: "x".into(),
+        }],
+        vec![Edit {
+            start: old.find("a.clone()").unwrap(),
+            end: old.find("a.clone()").unwrap() + 9,
+            text: "a".into(),
+        }],
+    ];
+    let new = apply_edits(old, &edits);
+    expect_test::expect![[r#"
+        diff --git a/src/main.rs b/src/main.rs
+        --- a/src/main.rs
+        +++ b/src/main.rs
+        @@ -1,5 +1,5 @@
+         fn main() {
+        -    let a = 1;
+        +    x = 1;
+             let b = 2;
+             let c = 3;
+             let d = 4;
+        @@ -7,5 +7,5 @@
+             let f = 6;
+             let g = 7;
+             let h = 8;
+        -    println!("{}", a.clone());
+        +    println!("{}", a);
+         }
+        \ No newline at end of file
+    "#]]
+    .assert_eq(&unified_diff("src/main.rs", old, &new));
+}
+
+#[test]
+fn fmt_mismatches_to_unified_diff() {
+    let old = "fn main() {\nlet a = 1;\n    let b  =  2;\n\n\n}\n";
+    let mismatches = [
+        FmtMismatch {
+            original_begin_line: 2,
+            original_end_line: 3,
+            expected_begin_line: 2,
+            expected_end_line: 3,
+            original: "let a = 1;\n    let b  =  2;".into(),
+            expected: "    let a = 1;\n    let b = 2;".into(),
+        },
+        FmtMismatch {
+            original_begin_line: 4,
+            original_end_line: 5,
+            expected_begin_line: 4,
+            expected_end_line: 3,
+            original: "\n".into(),
+            expected: "".into(),
+        },
+    ];
+    let line_starts = line_starts(old);
+    let edits: Vec<_> = mismatches
+        .iter()
+        .map(|mis| vec![fmt_edit(old, &line_starts, mis).unwrap()])
+        .collect();
+    let new = apply_edits(old, &edits);
+    assert_eq!(new, "fn main() {\n    let a = 1;\n    let b = 2;\n}\n");
+    expect_test::expect![[r#"
+        diff --git a/src/main.rs b/src/main.rs
+        --- a/src/main.rs
+        +++ b/src/main.rs
+        @@ -1,6 +1,4 @@
+         fn main() {
+        -let a = 1;
+        -    let b  =  2;
+        -
+        -
+        +    let a = 1;
+        +    let b = 2;
+         }
+    "#]]
+    .assert_eq(&unified_diff("src/main.rs", old, &new));
+}
+
+#[test]
+fn overlapping_suggestion_is_skipped_as_a_whole() {
+    let old = "let a = b.clone().clone();";
+    let edit = |start, end, text: &str| Edit {
+        start,
+        end,
+        text: text.into(),
+    };
+    let groups = [
+        // `b.clone()` => `b`
+        vec![edit(8, 17, "b")],
+        // a suggestion with two spans, one of which overlaps with the above
+        vec![edit(9, 10, "?."), edit(17, 25, "")],
+        vec![edit(0, 3, "const")],
+    ];
+    assert_eq!(apply_edits(old, &groups), "const a = b.clone();");
+}
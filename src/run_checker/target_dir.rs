@@ -0,0 +1,169 @@
+use crate::{
+    cli::{offline, target_dir_max_size, target_dir_root},
+    config::{CheckerTool, Resolve},
+    output::get_channel,
+    Result,
+};
+use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
+use duct::Expression;
+use std::fs;
+use CheckerTool::*;
+
+const CARGO_TARGET_DIR: &str = "CARGO_TARGET_DIR";
+/// 记录 target dir 最近一次被使用的时间（unix 毫秒），用于按大小清理
+const LAST_USED: &str = ".os-checker-last-used";
+
+/// 需要编译的检查工具
+fn builds(checker: CheckerTool) -> bool {
+    matches!(
+        checker,
+        Clippy
+            | Miri
+            | SemverChecks
+            | Mirai
+            | Lockbud
+            | Atomvchecker
+            | Rapx
+            | Rudra
+            | Geiger
+            | Udeps
+    )
+}
+
+/// 在编译过程中分析目标 package 的检查工具：增量编译跳过未改动的 package 时，
+/// 它们不会输出任何结果（比如 lockbud 需要旧 MIR），因此运行前清除该 package
+/// 自身的编译产物，但保留依赖的编译产物。
+fn needs_fresh_build(checker: CheckerTool) -> bool {
+    matches!(
+        checker,
+        Mirai | Lockbud | Atomvchecker | Rapx | Rudra | Geiger
+    )
+}
+
+/// 该检查命令使用的 target dir：每个检查工具与工具链的组合独立，
+/// 在所有仓库之间共享依赖的编译产物。配置文件在 env 中指定 CARGO_TARGET_DIR 时不做处理。
+fn target_dir(resolve: &Resolve) -> Option<Utf8PathBuf> {
+    if !builds(resolve.checker) || resolve.env.contains_key(CARGO_TARGET_DIR) {
+        return None;
+    }
+    let channel = get_channel(resolve.toolchain.unwrap_or(0));
+    let name = format!("{}-{channel}", resolve.checker.name());
+    Some(target_dir_root().join(name))
+}
+
+/// 清除该 package 自身的编译产物：在计时之前调用，以免影响检查耗时。
+/// 使用与检查命令相同的环境变量，因此离线模式下也不会访问网络。
+pub fn clean(resolve: &Resolve) {
+    let Some(dir) = target_dir(resolve) else {
+        return;
+    };
+    if !needs_fresh_build(resolve.checker) || !dir.exists() {
+        return;
+    }
+    let pkg = resolve.pkg_name.as_str();
+    let mut clean = duct::cmd!("cargo", "clean", "-p", pkg)
+        .dir(&resolve.pkg_dir)
+        .env(CARGO_TARGET_DIR, &dir);
+    for (name, val) in &resolve.env {
+        clean = clean.env(name, val);
+    }
+    if offline() {
+        clean = clean.env("CARGO_NET_OFFLINE", "true");
+    }
+    if let Err(err) = clean.stdout_null().stderr_capture().run() {
+        error!(?resolve.checker, pkg, %dir, ?err, "Failed to call cargo clean -p.");
+    }
+}
+
+/// 返回设置了 CARGO_TARGET_DIR 的检查命令。
+pub fn expr(resolve: &Resolve) -> Expression {
+    let Some(dir) = target_dir(resolve) else {
+        return resolve.expr.clone();
+    };
+    if let Err(err) = touch(&dir) {
+        error!(%dir, ?err, "Failed to create the target dir.");
+    }
+    resolve.expr.clone().env(CARGO_TARGET_DIR, dir)
+}
+
+fn touch(dir: &Utf8Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(LAST_USED), os_checker_types::now().to_string())?;
+    Ok(())
+}
+
+/// 当 target dirs 的总大小超过 `--target-dir-max-size` 时，从最久未使用的开始删除。
+/// 在检查每个仓库之前调用。
+pub fn prune() {
+    let Some(max) = target_dir_max_size() else {
+        return;
+    };
+    let root = target_dir_root();
+    match prune_dir(&root, max) {
+        Ok(removed) if !removed.is_empty() => info!(?removed, "pruned target dirs"),
+        Ok(_) => (),
+        Err(err) => error!(%root, ?err, "Failed to prune target dirs."),
+    }
+}
+
+/// 返回被删除的目录名。
+fn prune_dir(root: &Utf8Path, max: u64) -> Result<Vec<String>> {
+    if !root.exists() {
+        return Ok(vec![]);
+    }
+    let mut dirs = Vec::new();
+    for entry in root.read_dir_utf8()? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            let path = entry.into_path();
+            let last_used = fs::read_to_string(path.join(LAST_USED))
+                .ok()
+                .and_then(|s| s.trim().parse::<u64>().ok())
+                .unwrap_or(0);
+            dirs.push((last_used, dir_size(&path), path));
+        }
+    }
+    dirs.sort_unstable_by_key(|(last_used, _, _)| *last_used);
+
+    let mut total: u64 = dirs.iter().map(|(_, size, _)| size).sum();
+    let mut removed = Vec::new();
+    for (_, size, path) in dirs {
+        if total <= max {
+            break;
+        }
+        fs::remove_dir_all(&path)?;
+        total -= size;
+        removed.push(path.file_name().unwrap_or_default().to_owned());
+    }
+    Ok(removed)
+}
+
+fn dir_size(dir: &Utf8Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+#[test]
+fn prune_least_recently_used() -> Result<()> {
+    let root = std::env::temp_dir().join(format!("os-checker-target-{}", std::process::id()));
+    let root = Utf8PathBuf::from_path_buf(root).unwrap();
+    for (name, last_used, size) in [("a", 3, 100), ("b", 1, 200), ("c", 2, 300)] {
+        let dir = root.join(name).join("debug");
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("lib.rlib"), vec![0u8; size])?;
+        fs::write(root.join(name).join(LAST_USED), format!("{last_used}"))?;
+    }
+    let exists = |dir: &str| root.join(dir).exists();
+
+    assert!(prune_dir(&root, 10_000)?.is_empty());
+    // b 最久未使用，删除它之后仍然超过上限，因此继续删除 c
+    assert_eq!(prune_dir(&root, 300)?, ["b", "c"]);
+    assert!(exists("a") && !exists("b") && !exists("c"));
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
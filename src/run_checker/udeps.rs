@@ -0,0 +1,120 @@
+use crate::{config::Resolve, Result};
+use cargo_metadata::camino::Utf8PathBuf;
+use eyre::Context;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::fmt;
+
+/// The output of `cargo udeps --output json`.
+#[derive(Debug, Deserialize)]
+struct UdepsOutcome {
+    /// Keys are package ids.
+    unused_deps: IndexMap<String, UdepsPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UdepsPackage {
+    manifest_path: Utf8PathBuf,
+    #[serde(default)]
+    normal: Vec<String>,
+    #[serde(default)]
+    development: Vec<String>,
+    #[serde(default)]
+    build: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DepKind {
+    Normal,
+    Development,
+    Build,
+}
+
+impl DepKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            DepKind::Normal => "normal",
+            DepKind::Development => "dev",
+            DepKind::Build => "build",
+        }
+    }
+}
+
+/// An unused dependency declared in the manifest.
+#[derive(Debug)]
+pub struct UnusedDep {
+    pub manifest_path: Utf8PathBuf,
+    pub name: String,
+    pub kind: DepKind,
+}
+
+impl fmt::Display for UnusedDep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unused {} dependency `{}`", self.kind.name(), self.name)
+    }
+}
+
+pub fn parse(out: &std::process::Output, resolve: &Resolve) -> Result<Box<[UnusedDep]>> {
+    // cargo-udeps exits with 1 when unused deps are found, and with the same code
+    // when the compilation fails; the latter has no JSON in stdout.
+    if out.status.success() && out.stdout.trim_ascii().is_empty() {
+        return Ok(Box::default());
+    }
+    parse_stdout(&out.stdout).with_context(|| {
+        format!(
+            "无法解析 cargo-udeps 的标准输出：stdout={}\n原始命令为：`{}`\nstderr={}",
+            String::from_utf8_lossy(&out.stdout),
+            resolve.cmd,
+            String::from_utf8(strip_ansi_escapes::strip(&out.stderr))
+                .unwrap_or_else(|_| "Stderr contains non UTF8 chars.".to_owned())
+        )
+    })
+}
+
+fn parse_stdout(stdout: &[u8]) -> Result<Box<[UnusedDep]>> {
+    let outcome: UdepsOutcome = serde_json::from_slice(stdout)?;
+    let mut deps = Vec::new();
+    for pkg in outcome.unused_deps.into_values() {
+        let kinds = [
+            (DepKind::Normal, pkg.normal),
+            (DepKind::Development, pkg.development),
+            (DepKind::Build, pkg.build),
+        ];
+        for (kind, names) in kinds {
+            deps.extend(names.into_iter().map(|name| UnusedDep {
+                manifest_path: pkg.manifest_path.clone(),
+                name,
+                kind,
+            }));
+        }
+    }
+    Ok(deps.into())
+}
+
+#[test]
+fn parse_udeps_json() -> Result<()> {
+    let stdout = br#"{
+  "success": false,
+  "unused_deps": {
+    "foo 0.1.0 (path+file:///tmp/foo)": {
+      "manifest_path": "/tmp/foo/Cargo.toml",
+      "normal": ["regex"],
+      "development": ["expect-test"],
+      "build": []
+    }
+  },
+  "note": "Note: They might be false-positive."
+}"#;
+    let raw: Vec<_> = parse_stdout(stdout)?
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+    expect_test::expect![[r#"
+        [
+            "unused normal dependency `regex`",
+            "unused dev dependency `expect-test`",
+        ]
+    "#]]
+    .assert_debug_eq(&raw);
+    Ok(())
+}
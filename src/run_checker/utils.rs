@@ -1,4 +1,7 @@
 use super::{
+    outdated::OutdatedDep,
+    patch::find_file,
+    udeps::{DepKind, UnusedDep},
     CargoMessage, CargoSource, FmtMessage, Output as RawOutput, OutputParsed, RustcMessage,
     RustcTag,
 };
@@ -7,11 +10,15 @@ use crate::{
     db::{
         out::CacheLayout, CacheRepo, CacheRepoKey, CacheValue, Db, InfoKeyValue, OutputDataInner,
     },
-    layout::Audit,
-    output::{Cmd, Data, Kind},
+    layout::{AuditDiagnostic, AuditDiagnostics},
+    output::{Cmd, Data, Fingerprint, Kind, Sources},
     Result,
 };
-use cargo_metadata::camino::Utf8Path;
+use cargo_metadata::{
+    camino::{Utf8Path, Utf8PathBuf},
+    diagnostic::{Diagnostic, DiagnosticLevel, DiagnosticSpan},
+};
+use os_checker_types::{DataDetail, Severity, Span};
 use std::fmt::Write;
 
 /// 将一次工具的检查命令推入一次 `Vec<Idx>`，并把原始输出全部推入 `Vec<Data>`。
@@ -30,6 +37,7 @@ pub fn push_idx_and_data(
 impl RawOutput {
     pub fn to_cache(&self, db_repo: Option<DbRepo>) -> CacheValue {
         let root = &self.resolve.pkg_dir;
+        let checker = self.resolve.checker;
 
         // 由于路径的唯一性在这变得重要，需要提前归一化路径；两条思路：
         // * package_name 暗含了库的根目录，因此需要把路径的根目录去掉（选择了这条）
@@ -39,15 +47,15 @@ impl RawOutput {
             OutputParsed::Clippy(v) => data_rustc(CheckerTool::Clippy, v, root),
             OutputParsed::Audit(a) => data_audit(a, root),
             OutputParsed::Mirai(v) => data_rustc(CheckerTool::Mirai, v, root),
-            OutputParsed::Lockbud(s) => data_lockbud(s),
-            OutputParsed::Atomvchecker(s) => data_atomvchecker(s),
-            OutputParsed::Rap(s) => data_rap(s),
-            OutputParsed::Rudra(s) => data_rudra(s),
-            OutputParsed::Outdated(s) => data_outdated(s),
-            OutputParsed::Geiger(s) => data_geiger(s),
-            OutputParsed::SemverChecks(s) => data_semver_checks(s),
-            OutputParsed::Udeps(s) => data_udeps(s),
-            OutputParsed::Cargo { source, stderr } => data_cargo(source, stderr),
+            OutputParsed::Lockbud(s) => data_lockbud(s, root),
+            OutputParsed::Atomvchecker(s) => data_atomvchecker(s, root),
+            OutputParsed::Rap(s) => data_rap(s, root),
+            OutputParsed::Rudra(s) => data_rudra(s, root),
+            OutputParsed::Outdated(v) => data_outdated(v, root),
+            OutputParsed::Geiger(s) => data_geiger(s, root),
+            OutputParsed::SemverChecks(s) => data_semver_checks(s, root),
+            OutputParsed::Udeps(v) => data_udeps(v, root),
+            OutputParsed::Cargo { source, stderr } => data_cargo(checker, source, stderr, root),
         };
 
         let cache = CacheValue::new(&self.resolve, self.duration_ms, data);
@@ -106,16 +114,40 @@ impl<'a> DbRepo<'a> {
     }
 }
 
-fn data_cargo(source: &CargoSource, stderr: &str) -> Vec<OutputDataInner> {
+/// 对于没有 lint code 和 item 信息的诊断，指纹只取决于文件和归一化的原始输出
+fn new_data(
+    checker: CheckerTool,
+    file: Utf8PathBuf,
+    kind: Kind,
+    raw: String,
+    root: &Utf8Path,
+) -> OutputDataInner {
+    let fingerprint = Fingerprint::new(checker, kind, &raw, &file).hash(root);
+    let severity = match kind {
+        Kind::Cargo => Severity::Error,
+        _ => Severity::Warning,
+    };
+    OutputDataInner::new(file, kind, raw, fingerprint).with_detail(DataDetail {
+        severity: Some(severity),
+        ..Default::default()
+    })
+}
+
+fn data_cargo(
+    checker: CheckerTool,
+    source: &CargoSource,
+    stderr: &str,
+    root: &Utf8Path,
+) -> Vec<OutputDataInner> {
     let file = match source {
         CargoSource::Checker(checker) => format!("(virtual) {}", checker.name()).into(),
         CargoSource::LayoutParseError(repo_root) => (&**repo_root).into(),
     };
-    let data = OutputDataInner::new(file, Kind::Cargo, stderr.to_owned());
+    let data = new_data(checker, file, Kind::Cargo, stderr.to_owned(), root);
     vec![data]
 }
 
-fn data_lockbud(s: &str) -> Vec<OutputDataInner> {
+fn data_lockbud(s: &str, root: &Utf8Path) -> Vec<OutputDataInner> {
     if s.is_empty() {
         Vec::new()
     } else {
@@ -125,115 +157,226 @@ fn data_lockbud(s: &str) -> Vec<OutputDataInner> {
         } else {
             Kind::LockbudProbably
         };
-        let data = OutputDataInner::new("[Lockbud] deadlock detection".into(), kind, s.to_owned());
+        let data = new_data(
+            CheckerTool::Lockbud,
+            "[Lockbud] deadlock detection".into(),
+            kind,
+            s.to_owned(),
+            root,
+        );
         vec![data]
     }
 }
 
-fn data_atomvchecker(s: &str) -> Vec<OutputDataInner> {
+fn data_atomvchecker(s: &str, root: &Utf8Path) -> Vec<OutputDataInner> {
     if s.is_empty() {
         Vec::new()
     } else {
         // FIXME: 目前 atomvchecker 无法良好地解析，需要等它实现 JSON 输出才能更可靠地区分哪种
-        let data = OutputDataInner::new(
+        let data = new_data(
+            CheckerTool::Atomvchecker,
             "[AtomVChecker] memory ordering misuse detection".into(),
             Kind::Atomvchecker,
             s.to_owned(),
+            root,
         );
         vec![data]
     }
 }
 
-fn data_rap(s: &str) -> Vec<OutputDataInner> {
+fn data_rap(s: &str, root: &Utf8Path) -> Vec<OutputDataInner> {
     if s.is_empty() {
         Vec::new()
     } else {
         // FIXME: 目前 rap 无法良好地解析，需要等它实现 JSON 输出才能更可靠地区分哪种
-        let data = OutputDataInner::new(
+        let data = new_data(
+            CheckerTool::Rapx,
             "[Rapx] memory safety detection".into(),
             Kind::Rapx,
             s.to_owned(),
+            root,
         );
         vec![data]
     }
 }
 
-fn data_rudra(s: &str) -> Vec<OutputDataInner> {
+fn data_rudra(s: &str, root: &Utf8Path) -> Vec<OutputDataInner> {
     if s.is_empty() {
         Vec::new()
     } else {
         // FIXME: 目前 rudra 无法良好地解析，需要等它实现 JSON 输出才能更可靠地区分哪种
-        let data = OutputDataInner::new(
+        let data = new_data(
+            CheckerTool::Rudra,
             "[Rudra] Send/Sync and destructor safety detection".into(),
             Kind::Rudra,
             s.to_owned(),
+            root,
         );
         vec![data]
     }
 }
 
-fn data_outdated(s: &str) -> Vec<OutputDataInner> {
-    if s.is_empty() {
-        Vec::new()
-    } else {
-        let data = OutputDataInner::new(
-            "[outdated direct dependencies]".into(),
-            Kind::Outdated,
-            s.to_owned(),
-        );
-        vec![data]
+/// 每个过时的依赖项对应一条诊断，并指向 package 的 Cargo.toml（相对于仓库根目录）；
+/// 指纹不包含版本号，因此依赖发布新版本时诊断仍被视为同一条。
+fn data_outdated(v: &[OutdatedDep], root: &Utf8Path) -> Vec<OutputDataInner> {
+    if v.is_empty() {
+        return vec![];
     }
+    let manifest = match crate::utils::path_in_repo(root) {
+        Ok(dir) => dir.join("Cargo.toml"),
+        Err(err) => {
+            error!(%root, ?err, "Failed to get the pkg dir relative to the repo root.");
+            root.join("Cargo.toml")
+        }
+    };
+    let file = manifest.as_path();
+    v.iter()
+        .map(|dep| {
+            let kind = dep.kind.as_deref().unwrap_or("Normal");
+            let identity = format!("outdated {kind} dependency `{}`", dep.name);
+            let fingerprint =
+                Fingerprint::new(CheckerTool::Outdated, Kind::Outdated, &identity, file).hash(root);
+            // 优先建议升级到 semver 兼容的版本
+            let upgrade = [&dep.compat, &dep.latest]
+                .into_iter()
+                .find(|v| *v != "---" && **v != dep.project);
+            let detail = DataDetail {
+                severity: Some(Severity::Warning),
+                help: upgrade.map(|v| format!("upgrade `{}` to {v}", dep.name)),
+                ..Default::default()
+            };
+            OutputDataInner::new(
+                file.to_owned(),
+                Kind::Outdated,
+                dep.to_string(),
+                fingerprint,
+            )
+            .with_detail(detail)
+        })
+        .collect()
 }
 
-fn data_semver_checks(s: &str) -> Vec<OutputDataInner> {
+fn data_semver_checks(s: &str, root: &Utf8Path) -> Vec<OutputDataInner> {
     if s.is_empty() {
         Vec::new()
     } else {
-        let data = OutputDataInner::new(
+        let data = new_data(
+            CheckerTool::SemverChecks,
             "[semver checks violation]".into(),
             Kind::SemverViolation,
             s.to_owned(),
+            root,
         );
         vec![data]
     }
 }
 
-fn data_geiger(s: &str) -> Vec<OutputDataInner> {
+fn data_geiger(s: &str, root: &Utf8Path) -> Vec<OutputDataInner> {
     if s.is_empty() {
         Vec::new()
     } else {
-        let data = OutputDataInner::new(
+        let data = new_data(
+            CheckerTool::Geiger,
             "[Geiger] Unsafe code statistics".into(),
             Kind::Geiger,
             s.to_owned(),
+            root,
         );
         vec![data]
     }
 }
 
-fn data_udeps(s: &str) -> Vec<OutputDataInner> {
-    if s.is_empty() {
-        Vec::new()
-    } else {
-        let data = OutputDataInner::new(
-            "[Udeps] Unused dependencies".into(),
-            Kind::Udeps,
-            s.to_owned(),
-        );
-        vec![data]
-    }
+/// 每个未使用的依赖项对应一条诊断，并指向声明它的 Cargo.toml
+fn data_udeps(v: &[UnusedDep], root: &Utf8Path) -> Vec<OutputDataInner> {
+    v.iter()
+        .map(|dep| {
+            let file = strip_prefix(&dep.manifest_path, root).to_owned();
+            let section = match dep.kind {
+                DepKind::Normal => "dependencies",
+                DepKind::Development => "dev-dependencies",
+                DepKind::Build => "build-dependencies",
+            };
+            let detail = DataDetail {
+                severity: Some(Severity::Warning),
+                help: Some(format!("remove `{}` from [{section}]", dep.name)),
+                ..Default::default()
+            };
+            new_data(CheckerTool::Udeps, file, Kind::Udeps, dep.to_string(), root)
+                .with_detail(detail)
+        })
+        .collect()
 }
 
-fn data_audit(a: &Audit, root: &Utf8Path) -> Vec<OutputDataInner> {
-    if let Some(audit) = a {
-        let file = strip_prefix(audit.lock_file(), root).to_owned();
-        let raw = audit.output().to_owned();
-        vec![OutputDataInner::new(file, Kind::Audit, raw)]
-    } else {
-        vec![]
+fn data_audit(a: &Option<AuditDiagnostics>, root: &Utf8Path) -> Vec<OutputDataInner> {
+    let Some(audit) = a else { return vec![] };
+    let file = strip_prefix(&audit.lock_file, root);
+    let relative_lock_file = relative_lock_file(&audit.lock_file, root);
+    let lock_file = std::fs::read_to_string(&audit.lock_file).unwrap_or_default();
+    audit
+        .findings
+        .iter()
+        .map(|finding| {
+            let AuditDiagnostic {
+                id,
+                severity,
+                package,
+                help,
+                raw,
+            } = finding;
+            // 只使用 advisory id、受影响的 crate 和 Cargo.lock 路径，
+            // 因为 raw 中的依赖路径和版本会随着无关的依赖更新而变化
+            let fingerprint = Fingerprint::new(
+                CheckerTool::Audit,
+                Kind::Audit,
+                &package.0,
+                &relative_lock_file,
+            )
+            .code(Some(id))
+            .hash(root);
+            let detail = DataDetail {
+                span: lock_file_span(&lock_file, &package.0, &package.1),
+                code: Some(id.clone()),
+                severity: Some(*severity),
+                help: help.clone(),
+            };
+            OutputDataInner::new(file.to_owned(), Kind::Audit, raw.clone(), fingerprint)
+                .with_detail(detail)
+        })
+        .collect()
+}
+
+/// Cargo.lock 相对于 package 目录的路径：workspace members 的 Cargo.lock 位于上级目录，
+/// 比如 `../../Cargo.lock`，从而不包含仓库的检出路径。
+fn relative_lock_file(lock_file: &Utf8Path, pkg_dir: &Utf8Path) -> Utf8PathBuf {
+    if let Ok(file) = lock_file.strip_prefix(pkg_dir) {
+        return file.to_owned();
+    }
+    let (Some(dir), Some(name)) = (lock_file.parent(), lock_file.file_name()) else {
+        return lock_file.to_owned();
+    };
+    match pkg_dir.strip_prefix(dir) {
+        Ok(sub) => sub.components().map(|_| "..").chain([name]).collect(),
+        Err(_) => lock_file.to_owned(),
     }
 }
 
+/// 找到 Cargo.lock 中 `[[package]]` 的 name 和 version 所在行
+fn lock_file_span(lock_file: &str, name: &str, version: &str) -> Option<Span> {
+    let name = format!("name = \"{name}\"");
+    let version = format!("version = \"{version}\"");
+    let lines: Vec<_> = lock_file.lines().collect();
+    let idx = lines
+        .windows(2)
+        .position(|w| w[0] == name && w[1] == version)?;
+    let line = idx as u32 + 1;
+    Some(Span {
+        line_start: line,
+        line_end: line + 1,
+        column_start: None,
+        column_end: None,
+    })
+}
+
 /// 尽可能缩短绝对路径到相对路径
 fn strip_prefix<'f>(file: &'f Utf8Path, root: &Utf8Path) -> &'f Utf8Path {
     file.strip_prefix(root).unwrap_or(file)
@@ -283,27 +426,47 @@ fn raw_message_fmt(mes: &FmtMessage) -> impl '_ + ExactSizeIterator<Item = Strin
 
 fn data_unformatted(v: &[FmtMessage], root: &Utf8Path) -> Vec<OutputDataInner> {
     let mut res = Vec::with_capacity(v.iter().map(|mes| mes.mismatches.len()).sum());
+    let mut sources = Sources::default();
     for mes in v {
         // NOTE: 该路径似乎是绝对路径
         let file = strip_prefix(&mes.name, root);
-        let iter = raw_message_fmt(mes)
-            .map(|raw| OutputDataInner::new(file.to_owned(), Kind::Unformatted, raw));
+        let iter = raw_message_fmt(mes).zip(&mes.mismatches).map(|(raw, mis)| {
+            let item = sources.enclosing_item(&mes.name, mis.original_begin_line as usize);
+            let fingerprint =
+                Fingerprint::new(CheckerTool::Fmt, Kind::Unformatted, &mis.original, file)
+                    .item(item.as_deref())
+                    .hash(root);
+            let detail = DataDetail {
+                span: Some(Span {
+                    line_start: mis.original_begin_line,
+                    line_end: mis.original_end_line,
+                    column_start: None,
+                    column_end: None,
+                }),
+                code: None,
+                severity: Some(Severity::Warning),
+                help: Some(format!("format as:\n{}", mis.expected)),
+            };
+            OutputDataInner::new(file.to_owned(), Kind::Unformatted, raw, fingerprint)
+                .with_detail(detail)
+        });
         res.extend(iter);
     }
     res
 }
 
 fn data_rustc(checker: CheckerTool, v: &[RustcMessage], root: &Utf8Path) -> Vec<OutputDataInner> {
-    fn raw_message_clippy(mes: &RustcMessage) -> Option<String> {
+    fn compiler_message(mes: &RustcMessage) -> Option<(&Diagnostic, String)> {
         if let CargoMessage::CompilerMessage(cmes) = &mes.inner {
             if let Some(render) = &cmes.message.rendered {
-                return Some(render.clone());
+                return Some((&cmes.message, render.clone()));
             }
         }
         None
     }
 
     let mut res = Vec::with_capacity(128);
+    let mut sources = Sources::default();
 
     for mes in v {
         // NOTE: 该路径似乎是相对路径，但为了防止意外的绝对路径，统一去除前缀。
@@ -311,35 +474,142 @@ fn data_rustc(checker: CheckerTool, v: &[RustcMessage], root: &Utf8Path) -> Vec<
         // 因为它们不处于同一个前缀。因此，我们需要根据处理后的路径是绝对还是相对路径来判断该文件位于
         // package 内部还是外部。
         // NOTE: --no-deps 目前有 bug，见 https://github.com/os-checker/bug-MRE-clippy-no-deps
-        match &mes.tag {
-            RustcTag::WarnDetailed(paths) => {
-                for path in paths {
-                    let file = strip_prefix(path, root);
-                    if let Some(raw) = raw_message_clippy(mes) {
-                        let kind = match checker {
-                            CheckerTool::Clippy => Kind::ClippyWarn,
-                            CheckerTool::Mirai => Kind::Mirai,
-                            _ => unreachable!("该函数只针对 rustc 风格的诊断"),
-                        };
-                        res.push(OutputDataInner::new(file.to_owned(), kind, raw));
-                    };
-                }
+        let (paths, kind) = match (&mes.tag, checker) {
+            (RustcTag::WarnDetailed(paths), CheckerTool::Clippy) => (paths, Kind::ClippyWarn),
+            (RustcTag::ErrorDetailed(paths), CheckerTool::Clippy) => (paths, Kind::ClippyError),
+            (
+                RustcTag::WarnDetailed(paths) | RustcTag::ErrorDetailed(paths),
+                CheckerTool::Mirai,
+            ) => (paths, Kind::Mirai),
+            (RustcTag::WarnDetailed(_) | RustcTag::ErrorDetailed(_), _) => {
+                unreachable!("该函数只针对 rustc 风格的诊断")
             }
-            RustcTag::ErrorDetailed(paths) => {
-                for path in paths {
-                    let file = strip_prefix(path, root);
-                    if let Some(raw) = raw_message_clippy(mes) {
-                        let kind = match checker {
-                            CheckerTool::Clippy => Kind::ClippyError,
-                            CheckerTool::Mirai => Kind::Mirai,
-                            _ => unreachable!("该函数只针对 rustc 风格的诊断"),
-                        };
-                        res.push(OutputDataInner::new(file.to_owned(), kind, raw));
-                    };
-                }
-            }
-            _ => (),
+            _ => continue,
+        };
+        let Some((diag, raw)) = compiler_message(mes) else {
+            continue;
+        };
+        for path in paths {
+            let file = strip_prefix(path, root);
+            let primary = diag
+                .spans
+                .iter()
+                .find(|span| span.is_primary && span.file_name == path.as_str());
+            let item = primary.and_then(|span| {
+                let path = find_file(&span.file_name, root)?;
+                sources.enclosing_item(&path, span.line_start)
+            });
+            let fingerprint = Fingerprint::new(checker, kind, &diag.message, file)
+                .code(diag.code.as_ref().map(|c| &*c.code))
+                .item(item.as_deref())
+                .hash(root);
+            let detail = DataDetail {
+                span: primary.map(rustc_span),
+                code: diag.code.as_ref().map(|c| c.code.clone()),
+                severity: rustc_severity(diag.level),
+                help: rustc_help(diag),
+            };
+            res.push(
+                OutputDataInner::new(file.to_owned(), kind, raw.clone(), fingerprint)
+                    .with_detail(detail),
+            );
         }
     }
     res
 }
+
+fn rustc_span(span: &DiagnosticSpan) -> Span {
+    Span {
+        line_start: span.line_start as u32,
+        line_end: span.line_end as u32,
+        column_start: Some(span.column_start as u32),
+        column_end: Some(span.column_end as u32),
+    }
+}
+
+fn rustc_severity(level: DiagnosticLevel) -> Option<Severity> {
+    Some(match level {
+        DiagnosticLevel::Ice | DiagnosticLevel::Error => Severity::Error,
+        DiagnosticLevel::Warning => Severity::Warning,
+        DiagnosticLevel::FailureNote | DiagnosticLevel::Note => Severity::Note,
+        DiagnosticLevel::Help => Severity::Help,
+        _ => return None,
+    })
+}
+
+/// 收集 help 子诊断，如果带有建议的替换代码，则附在后面
+fn rustc_help(diag: &Diagnostic) -> Option<String> {
+    let help: Vec<_> = diag
+        .children
+        .iter()
+        .filter(|child| matches!(child.level, DiagnosticLevel::Help))
+        .map(|child| {
+            let replacements: Vec<_> = child
+                .spans
+                .iter()
+                .filter_map(|span| span.suggested_replacement.as_deref())
+                .collect();
+            if replacements.is_empty() {
+                child.message.clone()
+            } else {
+                format!("{}: `{}`", child.message, replacements.join("`, `"))
+            }
+        })
+        .collect();
+    (!help.is_empty()).then(|| help.join("\n"))
+}
+
+#[test]
+fn rustc_detail() {
+    let diag: Diagnostic = serde_json::from_str(
+        r#"{
+  "message": "unneeded `return` statement",
+  "code": {"code": "clippy::needless_return", "explanation": null},
+  "level": "warning",
+  "spans": [{"file_name": "src/lib.rs", "byte_start": 30, "byte_end": 39,
+    "line_start": 3, "line_end": 3, "column_start": 5, "column_end": 14,
+    "is_primary": true, "text": [], "label": null, "suggested_replacement": null,
+    "suggestion_applicability": null, "expansion": null}],
+  "children": [{"message": "remove `return`", "code": null, "level": "help",
+    "spans": [{"file_name": "src/lib.rs", "byte_start": 30, "byte_end": 39,
+      "line_start": 3, "line_end": 3, "column_start": 5, "column_end": 14,
+      "is_primary": true, "text": [], "label": null, "suggested_replacement": "1",
+      "suggestion_applicability": "MachineApplicable", "expansion": null}],
+    "children": [], "rendered": null}],
+  "rendered": "warning: unneeded `return` statement"
+}"#,
+    )
+    .unwrap();
+    let detail = DataDetail {
+        span: diag.spans.first().map(rustc_span),
+        code: diag.code.as_ref().map(|c| c.code.clone()),
+        severity: rustc_severity(diag.level),
+        help: rustc_help(&diag),
+    };
+    expect_test::expect![[r#"
+        {
+          "span": {
+            "line_start": 3,
+            "line_end": 3,
+            "column_start": 5,
+            "column_end": 14
+          },
+          "code": "clippy::needless_return",
+          "severity": "warning",
+          "help": "remove `return`: `1`"
+        }"#]]
+    .assert_eq(&serde_json::to_string_pretty(&detail).unwrap());
+
+    let lock_file = "[[package]]\nname = \"a\"\nversion = \"0.1.0\"\n\n[[package]]\nname = \"b\"\nversion = \"1.0.0\"\n";
+    let span = lock_file_span(lock_file, "b", "1.0.0").unwrap();
+    assert_eq!((span.line_start, span.line_end), (6, 7));
+}
+
+#[test]
+fn audit_lock_file_relative_to_pkg() {
+    let lock_file = Utf8Path::new("/tmp/repos/user/repo/Cargo.lock");
+    let rel = |pkg_dir| relative_lock_file(lock_file, Utf8Path::new(pkg_dir));
+    assert_eq!(rel("/tmp/repos/user/repo"), "Cargo.lock");
+    assert_eq!(rel("/tmp/repos/user/repo/crates/a"), "../../Cargo.lock");
+    assert_eq!(rel("/elsewhere/a"), lock_file);
+}
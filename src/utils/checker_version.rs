@@ -0,0 +1,121 @@
+//! 检测检查工具的版本：版本号写入缓存键，因此升级检查工具之后，旧的缓存自然失效并重新检查。
+
+use super::{
+    PLUS_TOOLCHAIN_ATOMVCHECKER, PLUS_TOOLCHAIN_LOCKBUD, PLUS_TOOLCHAIN_MIRAI, PLUS_TOOLCHAIN_RAP,
+    PLUS_TOOLCHAIN_RUDRA,
+};
+use crate::{
+    config::{CheckerTool, Resolve},
+    output::{get_channel, get_toolchain, host_toolchain},
+};
+use duct::cmd;
+use indexmap::IndexMap;
+use std::sync::{LazyLock, Mutex};
+use CheckerTool::*;
+
+/// 键为 (checker, `+toolchain`)；值为 None 表示无法检测版本。
+type Versions = IndexMap<(CheckerTool, String), Option<String>>;
+
+/// 每次运行中，每个 (checker, toolchain) 只检测一次。
+static VERSIONS: LazyLock<Mutex<Versions>> = LazyLock::new(Default::default);
+
+/// 运行检查时所用的工具链，形如 `+nightly-2025-01-10`；audit 不依赖工具链，返回空字符串。
+fn plus_toolchain(resolve: &Resolve) -> Option<String> {
+    match (resolve.checker, resolve.toolchain) {
+        (Clippy, Some(idx)) if idx != 0 => Some(format!("+{}", get_toolchain(idx))),
+        (checker, _) => fixed_toolchain(checker),
+    }
+}
+
+/// 除 clippy 外，每个检查工具的工具链是固定的；clippy 默认使用主机工具链。
+fn fixed_toolchain(checker: CheckerTool) -> Option<String> {
+    Some(match checker {
+        Clippy | Fmt | Geiger | Outdated | SemverChecks | Udeps => host_toolchain(),
+        Lockbud => PLUS_TOOLCHAIN_LOCKBUD.to_owned(),
+        Atomvchecker => PLUS_TOOLCHAIN_ATOMVCHECKER.to_owned(),
+        Mirai => PLUS_TOOLCHAIN_MIRAI.to_owned(),
+        Rapx => PLUS_TOOLCHAIN_RAP.to_owned(),
+        Rudra => PLUS_TOOLCHAIN_RUDRA.to_owned(),
+        Audit => String::new(),
+        Miri | Cargo => return None,
+    })
+}
+
+/// 检查工具的版本：
+/// * cargo 子命令形式的工具使用 `cargo +toolchain <checker> --version`
+/// * rustc driver 形式的工具（lockbud 等）不一定支持 `--version`，甚至可能把它当作编译参数而开始分析，
+///   因此使用其固定工具链的 `rustc -V`，并附上 `cargo-<checker>` 可执行文件的修改时间，以便察觉重新安装
+pub fn checker_version(resolve: &Resolve) -> Option<String> {
+    version(resolve.checker, plus_toolchain(resolve)?)
+}
+
+/// 缓存键所记录的检查工具的当前版本；channel 为缓存键中的工具链（主机工具链记为 nightly-YYYY-MM-DD）。
+///
+/// 用于在直接返回整个仓库的缓存之前，判断检查工具是否已经升级。
+pub fn cached_checker_version(checker: CheckerTool, channel: &str) -> Option<String> {
+    let toolchain = match checker {
+        Clippy if channel != get_channel(0) => format!("+{channel}"),
+        _ => fixed_toolchain(checker)?,
+    };
+    version(checker, toolchain)
+}
+
+fn version(checker: CheckerTool, toolchain: String) -> Option<String> {
+    let key = (checker, toolchain);
+    if let Some(version) = VERSIONS.lock().unwrap().get(&key) {
+        return version.clone();
+    }
+
+    let (checker, toolchain) = &key;
+    let version = match checker {
+        Lockbud | Atomvchecker | Mirai | Rapx | Rudra => detect_driver(checker.name(), toolchain),
+        _ => detect_subcommand(checker.name(), toolchain),
+    };
+    info!(?checker, toolchain, ?version, "detected checker version");
+    VERSIONS
+        .lock()
+        .unwrap()
+        .insert(key.clone(), version.clone());
+    version
+}
+
+fn first_line(expr: duct::Expression) -> Option<String> {
+    let output = expr.stdout_capture().stderr_null().unchecked().run().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next()?.trim();
+    (!line.is_empty()).then(|| line.to_owned())
+}
+
+fn detect_subcommand(checker: &str, toolchain: &str) -> Option<String> {
+    let args = [toolchain, checker, "--version"];
+    first_line(cmd("cargo", args.iter().filter(|arg| !arg.is_empty())))
+}
+
+fn detect_driver(checker: &str, toolchain: &str) -> Option<String> {
+    let rustc = first_line(cmd!("rustc", toolchain, "-V"))?;
+    let bin = format!("cargo-{checker}");
+    let modified = first_line(cmd!("which", &bin))
+        .and_then(|path| std::fs::metadata(path).ok()?.modified().ok())
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok());
+    Some(match modified {
+        Some(time) => format!("{rustc}; {bin} modified at {}", time.as_secs()),
+        None => rustc,
+    })
+}
+
+/// 所有已检测的检查工具版本，按 checker 排序：用于输出 `env.tools.checkers`。
+pub fn checker_versions() -> Vec<(CheckerTool, String, String)> {
+    let map = VERSIONS.lock().unwrap();
+    let mut v: Vec<_> = map
+        .iter()
+        .filter_map(|((checker, toolchain), version)| {
+            let toolchain = toolchain.trim_start_matches('+').to_owned();
+            Some((*checker, toolchain, version.clone()?))
+        })
+        .collect();
+    v.sort_unstable();
+    v
+}
@@ -3,10 +3,12 @@ use std::{env::var, sync::LazyLock};
 
 const FORCE_REPO_CHECK: &str = "FORCE_REPO_CHECK";
 const FORCE_RUN_CHECK: &str = "FORCE_RUN_CHECK";
+const REMOTE_INFO: &str = "REMOTE_INFO";
 
 struct Global {
     force_repo_check: bool,
     force_run_check: ForceRunCheck,
+    remote_info: RemoteInfo,
 }
 
 fn var_bool(env: &str) -> Option<bool> {
@@ -16,6 +18,7 @@ fn var_bool(env: &str) -> Option<bool> {
 static GLOBAL: LazyLock<Global> = LazyLock::new(|| Global {
     force_repo_check: var_bool(FORCE_REPO_CHECK).unwrap_or(false),
     force_run_check: ForceRunCheck::new(),
+    remote_info: RemoteInfo::new(),
 });
 
 /// 当 os-checker 内部支持新检查时，将这个值设置为 true，
@@ -72,3 +75,38 @@ impl ForceRunCheck {
 pub fn force_run_check() -> &'static ForceRunCheck {
     &GLOBAL.force_run_check
 }
+
+/// 获取远程仓库的默认分支和最新提交的方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteInfo {
+    /// Github 仓库并且 gh 命令可用时使用 Github API，否则使用 git
+    Auto,
+    /// Github 仓库总是使用 Github API（需要 gh 和 GH_TOKEN）
+    Github,
+    /// 总是使用 git ls-remote 和 git fetch，适用于任何 git 仓库
+    Git,
+}
+
+impl RemoteInfo {
+    /// * REMOTE_INFO=github => Github
+    /// * REMOTE_INFO=git => Git
+    /// * REMOTE_INFO=auto or unset => Auto
+    ///
+    /// Invalid value will just panic.
+    fn new() -> Self {
+        match var(REMOTE_INFO).map(|s| s.trim().to_ascii_lowercase()) {
+            Ok(var) if !var.is_empty() => match &*var {
+                "auto" => RemoteInfo::Auto,
+                "github" | "gh" => RemoteInfo::Github,
+                "git" => RemoteInfo::Git,
+                _ => panic!("{REMOTE_INFO}={var} is invalid: only accept auto, github, or git."),
+            },
+            _ => RemoteInfo::Auto,
+        }
+    }
+}
+
+/// 见 [`RemoteInfo`]。
+pub fn remote_info() -> RemoteInfo {
+    GLOBAL.remote_info
+}
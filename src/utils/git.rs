@@ -0,0 +1,512 @@
+//! git 克隆、更新与检出。
+use crate::{cli::offline, config::Submodules, Result};
+use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
+use duct::cmd;
+use eyre::Context;
+use std::time::Instant;
+
+/// 克隆选项：来自 meta.clone，sparse checkout 的模式来自 meta.only_pkg_dir_globs。
+#[derive(Debug, Default)]
+pub struct CloneOptions {
+    /// 浅克隆的提交深度
+    pub depth: Option<u32>,
+    /// 部分克隆的过滤器，比如 `blob:none`
+    pub filter: Option<String>,
+    pub submodules: Submodules,
+    /// sparse checkout 的模式（gitignore 语法）；为空表示完整检出
+    pub sparse: Vec<String>,
+}
+
+impl CloneOptions {
+    fn depth(&self) -> Option<String> {
+        self.depth.map(|depth| format!("--depth={depth}"))
+    }
+}
+
+/// git clone 一个仓库到一个 dir；如果该仓库已存在，则拉取最新的代码。
+/// 指定 rev 时检出该分支、标签或者提交。返回耗时（毫秒）。
+///
+/// `run --offline` 时不拉取：只使用已存在的仓库目录，或者克隆本地仓库。
+pub fn git_clone(dir: &Utf8Path, url: &str, opts: &CloneOptions, rev: Option<&str>) -> Result<u64> {
+    let now = Instant::now();
+    let _span = error_span!("git_clone", url, %dir).entered();
+
+    if dir.exists() {
+        // 检出 rev 之后处于 detached HEAD，无法 git pull，由 checkout 负责拉取
+        if rev.is_none() && !offline() {
+            let mut args = vec!["pull".to_owned()];
+            args.extend(opts.depth());
+            git(Some(dir), &args)?;
+        }
+    } else {
+        ensure!(
+            !offline() || is_local_url(url),
+            "offline: {dir} doesn't exist and {url} isn't a local repo; \
+             clone it before running with --offline"
+        );
+        let mut args = vec!["clone".to_owned()];
+        args.extend(opts.depth());
+        args.extend(opts.filter.as_ref().map(|f| format!("--filter={f}")));
+        if !opts.sparse.is_empty() {
+            args.push("--no-checkout".to_owned());
+        }
+        args.extend([url.to_owned(), dir.to_string()]);
+        git(None, &args)?;
+
+        if !opts.sparse.is_empty() {
+            sparse_set(dir, &opts.sparse)?;
+            if rev.is_none() {
+                git(Some(dir), &["checkout".to_owned()])?;
+            }
+        }
+    }
+
+    if let Some(rev) = rev {
+        let sha = checkout(dir, rev, opts)?;
+        debug!(rev, sha, "git checkout");
+    }
+    if !opts.sparse.is_empty() {
+        sparse_workspace(dir, &opts.sparse)?;
+    }
+    update_submodules(dir, opts.submodules)?;
+
+    Ok(now.elapsed().as_millis() as u64)
+}
+
+fn sparse_set(dir: &Utf8Path, patterns: &[String]) -> Result<()> {
+    let mut args = ["sparse-checkout", "set", "--no-cone"]
+        .map(String::from)
+        .to_vec();
+    args.extend(patterns.iter().cloned());
+    git(Some(dir), &args)?;
+    Ok(())
+}
+
+/// cargo 需要 workspace members 以及 path 依赖所在的目录，否则 cargo metadata 失败：
+/// 因此把检出的 Cargo.toml 所引用的目录加入 sparse checkout 的模式，直到不再发现新的目录。
+fn sparse_workspace(dir: &Utf8Path, base: &[String]) -> Result<()> {
+    let mut patterns = base.to_vec();
+    // path 依赖可能引用更多的目录，但层数有限
+    for _ in 0..8 {
+        let mut added = Vec::new();
+        for manifest in checked_out_manifests(dir) {
+            let src = std::fs::read_to_string(&manifest)?;
+            // 无效的 Cargo.toml 交由 cargo 报告
+            let Ok(toml) = basic_toml::from_str::<serde_json::Value>(&src) else {
+                warn!(%manifest, "unable to parse the manifest");
+                continue;
+            };
+            let manifest_dir = manifest.parent().unwrap_or(dir);
+            for path in manifest_paths(&toml) {
+                let Some(rel) = path_in_checkout(dir, &manifest_dir.join(path)) else {
+                    continue;
+                };
+                let is_glob = rel.as_str().contains(['*', '?', '[']);
+                if rel.as_str().is_empty() || (!is_glob && dir.join(&rel).exists()) {
+                    continue;
+                }
+                let pattern = format!("/{rel}/");
+                if !patterns.contains(&pattern) && !added.contains(&pattern) {
+                    added.push(pattern);
+                }
+            }
+        }
+        if added.is_empty() {
+            return Ok(());
+        }
+        debug!(
+            ?added,
+            "sparse checkout workspace members and path dependencies"
+        );
+        patterns.extend(added);
+        sparse_set(dir, &patterns)?;
+    }
+    Ok(())
+}
+
+fn checked_out_manifests(dir: &Utf8Path) -> Vec<Utf8PathBuf> {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| !matches!(entry.file_name().to_str(), Some(".git" | "target")))
+        .filter_map(|entry| Utf8PathBuf::from_path_buf(entry.ok()?.into_path()).ok())
+        .filter(|path| path.file_name() == Some("Cargo.toml"))
+        .collect()
+}
+
+/// Cargo.toml 引用的目录：workspace members（可能含 glob）、`package.workspace`，
+/// 以及各种 dependencies 和 patch 表中的 path 依赖。
+fn manifest_paths(toml: &serde_json::Value) -> Vec<&str> {
+    fn dep_paths<'a>(value: &'a serde_json::Value, in_deps: bool, buf: &mut Vec<&'a str>) {
+        let Some(table) = value.as_object() else {
+            return;
+        };
+        for (key, value) in table {
+            match value {
+                serde_json::Value::String(path) if in_deps && key == "path" => buf.push(path),
+                _ => {
+                    let in_deps = in_deps || key.ends_with("dependencies") || key == "patch";
+                    dep_paths(value, in_deps, buf);
+                }
+            }
+        }
+    }
+
+    let mut paths = Vec::new();
+    let workspace = &toml["workspace"];
+    for key in ["members", "default-members"] {
+        if let Some(members) = workspace[key].as_array() {
+            paths.extend(members.iter().filter_map(|m| m.as_str()));
+        }
+    }
+    paths.extend(toml["package"]["workspace"].as_str());
+    dep_paths(toml, false, &mut paths);
+    paths
+}
+
+/// 相对于仓库目录的规范路径；位于仓库之外时为 None。
+fn path_in_checkout(dir: &Utf8Path, path: &Utf8Path) -> Option<Utf8PathBuf> {
+    use cargo_metadata::camino::Utf8Component;
+    let mut buf = Vec::new();
+    for component in path.strip_prefix(dir).ok()?.components() {
+        match component {
+            Utf8Component::Normal(c) => buf.push(c),
+            Utf8Component::ParentDir => _ = buf.pop()?,
+            Utf8Component::CurDir => (),
+            Utf8Component::RootDir | Utf8Component::Prefix(_) => return None,
+        }
+    }
+    Some(buf.join("/").into())
+}
+
+/// 检出指定的分支、标签或者提交 sha（处于 detached HEAD），返回检出的提交 sha。
+///
+/// 分支优先解析为远程分支 `origin/{rev}`，因此总是检出该分支的最新提交；
+/// 浅克隆只跟踪默认分支，因此找不到 rev 时单独拉取它。
+fn checkout(dir: &Utf8Path, rev: &str, opts: &CloneOptions) -> Result<String> {
+    let resolve = |rev: &str| rev_parse(dir, rev);
+    let dir = Some(dir);
+
+    // 完整的提交 sha 不会变化：已存在于本地则无需拉取
+    let is_sha = rev.len() == 40 && rev.bytes().all(|b| b.is_ascii_hexdigit());
+    if is_sha {
+        if let Ok(sha) = resolve(rev) {
+            git(
+                dir,
+                &["checkout", "--detach", "--force", &sha].map(String::from),
+            )?;
+            return Ok(sha);
+        }
+    }
+
+    let local = || resolve(&format!("origin/{rev}")).or_else(|_| resolve(rev));
+    if offline() {
+        let sha = local()
+            .with_context(|| format!("离线时无法在 {dir:?} 中找到分支、标签或者提交 `{rev}`"))?;
+        git(
+            dir,
+            &["checkout", "--detach", "--force", &sha].map(String::from),
+        )?;
+        return Ok(sha);
+    }
+
+    let mut args = ["fetch", "--tags", "--force"].map(String::from).to_vec();
+    args.extend(opts.depth());
+    args.push("origin".to_owned());
+    git(dir, &args)?;
+
+    let sha = match local() {
+        Ok(sha) => sha,
+        Err(_) => {
+            let mut args = vec!["fetch".to_owned()];
+            args.extend(opts.depth());
+            args.extend(["origin".to_owned(), rev.to_owned()]);
+            git(dir, &args)?;
+            resolve("FETCH_HEAD")
+                .with_context(|| format!("无法在 {dir:?} 中找到分支、标签或者提交 `{rev}`"))?
+        }
+    };
+    git(
+        dir,
+        &["checkout", "--detach", "--force", &sha].map(String::from),
+    )?;
+    Ok(sha)
+}
+
+/// 解析为提交 sha。
+fn rev_parse(dir: &Utf8Path, rev: &str) -> Result<String> {
+    let arg = format!("{rev}^{{commit}}");
+    git(
+        Some(dir),
+        &["rev-parse", "--verify", "--quiet", &arg].map(String::from),
+    )
+}
+
+/// 自 since 与 HEAD 的合并基准以来改动的文件（包括未提交的改动），路径相对于 dir。
+///
+/// since 优先解析为远程分支 `origin/{since}`；本地不存在时从 origin 拉取它。
+pub fn changed_files(dir: &Utf8Path, since: &str) -> Result<Vec<Utf8PathBuf>> {
+    let _span = error_span!("changed_files", %dir, since).entered();
+    let base = match rev_parse(dir, &format!("origin/{since}")).or_else(|_| rev_parse(dir, since)) {
+        Ok(sha) => sha,
+        Err(_) => {
+            ensure!(!offline(), "offline: can't find `{since}` in {dir}");
+            git(Some(dir), &["fetch", "origin", since].map(String::from))
+                .with_context(|| format!("无法在 {dir} 中找到或者拉取 `{since}`"))?;
+            rev_parse(dir, "FETCH_HEAD")?
+        }
+    };
+    let merge_base = git(Some(dir), &["merge-base", &base, "HEAD"].map(String::from))
+        .with_context(|| {
+            format!("无法找到 `{since}` 与 HEAD 的合并基准；浅克隆需要去除 meta.clone.depth")
+        })?;
+    let args = [
+        "diff",
+        "--name-only",
+        "--no-renames",
+        "--relative",
+        &merge_base,
+    ];
+    let diff = git(Some(dir), &args.map(String::from))?;
+    let files: Vec<_> = diff.lines().map(Utf8PathBuf::from).collect();
+    debug!(merge_base, files.len = files.len());
+    Ok(files)
+}
+
+/// dir 相对于仓库根目录的路径；dir 为仓库根目录时为空路径。
+pub fn path_in_repo(dir: &Utf8Path) -> Result<Utf8PathBuf> {
+    let prefix = git(Some(dir), &["rev-parse", "--show-prefix"].map(String::from))?;
+    Ok(Utf8PathBuf::from(prefix.trim_end_matches('/')))
+}
+
+fn update_submodules(dir: &Utf8Path, submodules: Submodules) -> Result<()> {
+    let mut args = ["submodule", "update", "--init", "--recursive"]
+        .map(String::from)
+        .to_vec();
+    match submodules {
+        Submodules::None => return Ok(()),
+        Submodules::Shallow => args.push("--depth=1".to_owned()),
+        Submodules::Recursive => (),
+    }
+    if offline() {
+        args.push("--no-fetch".to_owned());
+    }
+    git(Some(dir), &args)?;
+    Ok(())
+}
+
+/// 本地路径或者 `file://` 仓库：克隆它们无需网络。
+pub fn is_local_url(url: &str) -> bool {
+    url.starts_with("file://") || Utf8Path::new(url).exists()
+}
+
+/// 运行 git 命令并返回 stdout；失败时报告 stderr。
+fn git(dir: Option<&Utf8Path>, args: &[String]) -> Result<String> {
+    let mut expr = cmd("git", args)
+        .stderr_capture()
+        .stdout_capture()
+        .unchecked();
+    if let Some(dir) = dir {
+        expr = expr.dir(dir);
+    }
+    let output = expr.run()?;
+    ensure!(
+        output.status.success(),
+        "git {args:?} 执行失败\nstderr={}",
+        String::from_utf8_lossy(&output.stderr),
+    );
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// 把 only_pkg_dir_globs 转换成 sparse checkout 的模式：
+/// 总是检出根目录下的文件（比如 workspace 的 Cargo.toml 和 rust-toolchain）以及 .cargo 目录，
+/// glob 则相对于仓库根目录。
+pub fn sparse_patterns(globs: &[String]) -> Vec<String> {
+    let mut patterns = ["/*", "!/*/", "/.cargo/"].map(String::from).to_vec();
+    patterns.extend(globs.iter().map(|glob| {
+        let glob = glob.trim_start_matches("./").trim_start_matches('/');
+        format!("/{glob}")
+    }));
+    patterns
+}
+
+#[test]
+fn sparse_checkout_patterns() {
+    let globs = ["crates/kernel/**", "./os/*", "/libs"].map(String::from);
+    expect_test::expect![[r#"
+        [
+            "/*",
+            "!/*/",
+            "/.cargo/",
+            "/crates/kernel/**",
+            "/os/*",
+            "/libs",
+        ]
+    "#]]
+    .assert_debug_eq(&sparse_patterns(&globs));
+}
+
+#[test]
+fn sparse_shallow_clone() -> Result<()> {
+    let base = std::env::temp_dir().join(format!("os-checker-git-{}", std::process::id()));
+    let base = cargo_metadata::camino::Utf8PathBuf::from_path_buf(base).unwrap();
+    let [src, dst] = [base.join("src"), base.join("dst")];
+    std::fs::create_dir_all(src.join("crates/a"))?;
+    std::fs::create_dir_all(src.join("crates/b"))?;
+
+    let src_dir = Some(&*src);
+    let commit = |file: &str, tag: &str| -> Result<()> {
+        std::fs::write(src.join(file), tag)?;
+        git(src_dir, &["add", "-A"].map(String::from))?;
+        let args = [
+            "-c",
+            "user.name=a",
+            "-c",
+            "user.email=a@b",
+            "commit",
+            "-qm",
+            tag,
+        ];
+        git(src_dir, &args.map(String::from))?;
+        git(src_dir, &["tag", tag].map(String::from))?;
+        Ok(())
+    };
+    git(src_dir, &["init", "-q"].map(String::from))?;
+    std::fs::write(src.join("crates/b/lib.rs"), "")?;
+    commit("Cargo.toml", "v1")?;
+    commit("crates/a/lib.rs", "v2")?;
+
+    let opts = CloneOptions {
+        depth: Some(1),
+        submodules: Submodules::None,
+        sparse: sparse_patterns(&["crates/a/**".to_owned()]),
+        ..Default::default()
+    };
+    git_clone(&dst, &format!("file://{src}"), &opts, None)?;
+    let exists = |file: &str| dst.join(file).exists();
+    assert!(exists("Cargo.toml") && exists("crates/a/lib.rs") && !exists("crates/b/lib.rs"));
+
+    // 浅克隆之后检出更早的标签
+    git_clone(&dst, &format!("file://{src}"), &opts, Some("v1"))?;
+    assert_eq!(std::fs::read_to_string(dst.join("Cargo.toml"))?, "v1");
+    assert!(!exists("crates/a/lib.rs"));
+
+    std::fs::remove_dir_all(&base)?;
+    Ok(())
+}
+
+#[test]
+fn sparse_workspace_members() -> Result<()> {
+    let base = std::env::temp_dir().join(format!("os-checker-sparse-ws-{}", std::process::id()));
+    let base = Utf8PathBuf::from_path_buf(base).unwrap();
+    let [src, dst] = [base.join("src"), base.join("dst")];
+    let files = [
+        (
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\", \"libs/util\"]\n\n\
+             [workspace.dependencies]\nshared = { path = \"shared\" }\n",
+        ),
+        (
+            "crates/a/Cargo.toml",
+            "[package]\nname = \"a\"\n\n[lib]\npath = \"lib.rs\"\n\n\
+             [dependencies]\nlocal = { path = \"../../vendor/local\" }\n",
+        ),
+        ("crates/a/lib.rs", ""),
+        ("crates/b/Cargo.toml", "[package]\nname = \"b\"\n"),
+        ("libs/util/Cargo.toml", "[package]\nname = \"util\"\n"),
+        ("shared/Cargo.toml", "[package]\nname = \"shared\"\n"),
+        ("vendor/local/Cargo.toml", "[package]\nname = \"local\"\n"),
+        ("docs/README.md", ""),
+    ];
+    for (file, content) in files {
+        let path = src.join(file);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, content)?;
+    }
+    let run = |args: &[&str]| {
+        let args: Vec<_> = args.iter().map(|s| s.to_string()).collect();
+        git(Some(&src), &args)
+    };
+    run(&["init", "-q"])?;
+    run(&["add", "-A"])?;
+    run(&[
+        "-c",
+        "user.name=a",
+        "-c",
+        "user.email=a@b",
+        "commit",
+        "-qm",
+        "ws",
+    ])?;
+
+    let opts = CloneOptions {
+        submodules: Submodules::None,
+        sparse: sparse_patterns(&["crates/a/**".to_owned()]),
+        ..Default::default()
+    };
+    git_clone(&dst, &format!("file://{src}"), &opts, None)?;
+    let exists = |file: &str| dst.join(file).exists();
+    // members、workspace 依赖以及 members 的 path 依赖都被检出
+    for file in [
+        "crates/a/lib.rs",
+        "crates/b/Cargo.toml",
+        "libs/util/Cargo.toml",
+        "shared/Cargo.toml",
+        "vendor/local/Cargo.toml",
+    ] {
+        assert!(exists(file), "{file}");
+    }
+    assert!(!exists("docs/README.md"));
+
+    std::fs::remove_dir_all(&base)?;
+    Ok(())
+}
+
+#[test]
+fn changed_files_since() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("os-checker-since-{}", std::process::id()));
+    let dir = Utf8PathBuf::from_path_buf(dir).unwrap();
+    std::fs::create_dir_all(dir.join("crates/a"))?;
+
+    let run = |args: &[&str]| {
+        git(
+            Some(&dir),
+            &args.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+        )
+    };
+    let commit = |file: &str, msg: &str| -> Result<()> {
+        std::fs::write(dir.join(file), msg)?;
+        run(&["add", "-A"])?;
+        run(&[
+            "-c",
+            "user.name=a",
+            "-c",
+            "user.email=a@b",
+            "commit",
+            "-qm",
+            msg,
+        ])?;
+        Ok(())
+    };
+    run(&["init", "-q", "-b", "main"])?;
+    commit("Cargo.toml", "base")?;
+    run(&["checkout", "-q", "-b", "pr"])?;
+    commit("crates/a/lib.rs", "pr")?;
+    // main 上的后续提交不属于 pr 的改动
+    run(&["checkout", "-q", "main"])?;
+    commit("README.md", "main")?;
+    run(&["checkout", "-q", "pr"])?;
+    // 未提交的改动
+    std::fs::write(dir.join("Cargo.toml"), "dirty")?;
+
+    let mut files = changed_files(&dir, "main")?;
+    files.sort_unstable();
+    assert_eq!(files, ["Cargo.toml", "crates/a/lib.rs"]);
+
+    // 路径相对于子目录，并且只包含子目录内的改动
+    assert_eq!(changed_files(&dir.join("crates"), "main")?, ["a/lib.rs"]);
+    assert_eq!(path_in_repo(&dir.join("crates/a"))?, "crates/a");
+    assert_eq!(path_in_repo(&dir)?, "");
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
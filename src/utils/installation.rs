@@ -1,4 +1,4 @@
-use crate::Result;
+use crate::{cli::offline, Result};
 use cargo_metadata::camino::Utf8Path;
 use duct::{cmd, Expression};
 use eyre::Context;
@@ -29,8 +29,51 @@ pub fn rustup_target_add(targets: &[&str], dir: &Utf8Path) -> Result<()> {
     })
 }
 
+/// 离线时无法通过 rustup 安装工具链、targets 和组件，因此只检查它们是否已安装。
+/// kind 为 target 或者 component；names 为空时只检查工具链是否已安装。
+pub fn ensure_installed(channel: &str, kind: &str, names: &[&str]) -> Result<()> {
+    let output = cmd!(
+        "rustup",
+        kind,
+        "list",
+        "--installed",
+        "--toolchain",
+        channel
+    )
+    // 防止 rustup 自动安装缺少的工具链
+    .env("RUSTUP_AUTO_INSTALL", "0")
+    .unchecked()
+    .stdout_capture()
+    .stderr_capture()
+    .run()?;
+    ensure!(
+        output.status.success(),
+        "离线模式下无法安装工具链 {channel}，请预先安装它\nstderr={}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let list = String::from_utf8(output.stdout)?;
+    // 组件名带有 target 后缀，比如 clippy-x86_64-unknown-linux-gnu
+    let missing: Vec<_> = names
+        .iter()
+        .filter(|name| {
+            !list
+                .lines()
+                .any(|l| l == **name || l.starts_with(&format!("{name}-")))
+        })
+        .collect();
+    ensure!(
+        missing.is_empty(),
+        "离线模式下无法给工具链 {channel} 安装 {kind} {missing:?}，请预先安装它们"
+    );
+    Ok(())
+}
+
 pub fn rustup_target_add_for_checkers(targets: &[&str]) -> Result<()> {
     let install_targets = |toolchain: &'static str, target: &str| {
+        if offline() {
+            let channel = toolchain.trim_start_matches('+');
+            return ensure_installed(channel, "target", &[target]);
+        }
         let expr = cmd("rustup", [toolchain, "target", "add", target]);
         run_cmd(expr, || {
             format!("在 {toolchain} 工具链上安装 target {target:?} 失败")
@@ -110,3 +153,9 @@ fn which_checker() {
     detect_checker_if_exists("mirai").unwrap();
     detect_checker_if_exists("mirai2").unwrap();
 }
+
+#[test]
+fn offline_missing_toolchain() {
+    let err = ensure_installed("nightly-2000-01-01", "target", &[]).unwrap_err();
+    assert!(err.to_string().contains("nightly-2000-01-01"), "{err:?}");
+}
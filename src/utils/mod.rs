@@ -1,7 +1,4 @@
-use crate::Result;
-use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
-use duct::cmd;
-use eyre::Context;
+use cargo_metadata::camino::Utf8PathBuf;
 use std::{path::Path, time::Instant};
 
 pub mod env_var;
@@ -13,9 +10,22 @@ pub use scan_for_targets::scan_scripts_for_target;
 mod exlucded;
 pub use exlucded::{empty, Exclude};
 
+mod checker_version;
+pub use checker_version::{cached_checker_version, checker_version, checker_versions};
+
+mod git;
+pub use git::{
+    changed_files, git_clone, is_local_url, path_in_repo, sparse_patterns, CloneOptions,
+};
+
+/// `run --offline` 时跳过需要网络的检查工具
+mod offline;
+pub use offline::{offline_skipped, skipped_checkers};
+
 mod installation;
 pub use installation::{
-    init as installation_init, install_toolchain, rustup_target_add, rustup_target_add_for_checkers,
+    ensure_installed, init as installation_init, install_toolchain, rustup_target_add,
+    rustup_target_add_for_checkers,
 };
 
 /// 特殊的编译目标，os-checker 目前不支持在这上面运行检查。
@@ -33,25 +43,6 @@ pub const PLUS_TOOLCHAIN_LOCKBUD: &str = "+nightly-2025-02-01";
 pub const PLUS_TOOLCHAIN_ATOMVCHECKER: &str = "+nightly-2023-03-09";
 pub const PLUS_TOOLCHAIN_RAP: &str = "+nightly-2025-12-06";
 
-/// git clone 一个仓库到一个 dir。
-/// 如果该仓库已存在，则 git pull 拉取最新的代码。
-pub fn git_clone(dir: &Utf8Path, url: &str) -> Result<(std::process::Output, u64)> {
-    let now = std::time::Instant::now();
-    let output = if dir.exists() {
-        cmd!("git", "pull", "--recurse-submodules").dir(dir).run()?
-    } else {
-        cmd!("git", "clone", "--recursive", url, dir).run()?
-    };
-    let millis = now.elapsed().as_millis() as u64;
-    ensure!(
-        output.status.success(),
-        "git 获取 {url:?} 失败\nstderr={}\nstdout={}",
-        String::from_utf8_lossy(&output.stderr),
-        String::from_utf8_lossy(&output.stdout),
-    );
-    Ok((output, millis))
-}
-
 /// 遍历一个目录及其子目录的所有文件（但不进入 .git 和 target 目录）：
 /// * 需要设置一个最大递归深度（虽然可以不设置这个条件，但大部分情况下，os-checker 不需要深度递归）
 /// * op_on_file 为一个回调函数，其参数保证为一个文件路径，且返回值为 Some 时表示把它的值推到 Vec
@@ -129,33 +120,6 @@ pub fn execution_time_ms<T>(op: impl FnOnce() -> T) -> (time::OffsetDateTime, u6
 //     String::from_utf8(output.stdout).with_context(|| "stdout contains invalid UTF-8 chars")
 // }
 
-/// Same as `cmd_run`, but takes a callback to modify the cmd.
-pub fn cmd_run_with(
-    bin: &str,
-    args: &[&str],
-    dir: &Utf8Path,
-    ignore_fail: bool,
-    with: impl FnOnce(duct::Expression) -> duct::Expression,
-) -> Result<String> {
-    let _span = error_span!("cmd_run", bin, ?args).entered();
-
-    let output = with(
-        cmd(bin, args)
-            .dir(dir)
-            .unchecked()
-            .stdout_capture()
-            .stderr_capture(),
-    )
-    .run()?;
-
-    if !ignore_fail && !output.status.success() {
-        let raw_err = String::from_utf8_lossy(&output.stderr);
-        bail!("raw_err={raw_err}");
-    }
-
-    String::from_utf8(output.stdout).with_context(|| "stdout contains invalid UTF-8 chars")
-}
-
 #[test]
 fn test_walk_dir() {
     // NOTE: `**/os-checker-database/**` means all contents under os-checker-database dir,
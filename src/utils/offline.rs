@@ -0,0 +1,31 @@
+use crate::{cli::offline, config::CheckerTool, layout::advisory_db_dir};
+
+/// 离线时无法运行的检查工具及原因；非离线时为空。
+pub fn skipped_checkers() -> Vec<(CheckerTool, &'static str)> {
+    if !offline() {
+        return vec![];
+    }
+    let mut v = vec![
+        (
+            CheckerTool::SemverChecks,
+            "offline: needs the baseline from crates.io",
+        ),
+        (
+            CheckerTool::Outdated,
+            "offline: needs the latest versions from crates.io",
+        ),
+    ];
+    if !advisory_db_dir().is_some_and(|dir| dir.exists()) {
+        v.push((
+            CheckerTool::Audit,
+            "offline: no local advisory-db; specify one via --advisory-db",
+        ));
+    }
+    v.sort_unstable();
+    v
+}
+
+/// 该检查工具是否因为离线而被跳过。
+pub fn offline_skipped(checker: CheckerTool) -> bool {
+    skipped_checkers().iter().any(|(c, _)| *c == checker)
+}